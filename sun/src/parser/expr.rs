@@ -1,373 +1,960 @@
 use crate::vm::command::Command;
-use sun_core::{container::SunValue, utils::log::debug_output};
+use std::collections::HashMap;
+use sun_core::{
+    container::{Capture, SunValue, TableKey, UserFunction},
+    utils::{log::debug_output, Span},
+};
 
-/// 语法树节点
+/// 语法树节点，运行时可能出错的节点记录对应运算符的位置
 #[derive(Debug, Clone)]
 pub enum Expr {
-    Add(Box<Expr>, Box<Expr>),   // 0
-    Sub(Box<Expr>, Box<Expr>),   // 0
-    Mul(Box<Expr>, Box<Expr>),   // 1
-    Div(Box<Expr>, Box<Expr>),   // 1
-    Rem(Box<Expr>, Box<Expr>),   // 1
-    Neg(Box<Expr>),              // 3
-    And(Box<Expr>, Box<Expr>),   // 0
-    Or(Box<Expr>, Box<Expr>),    // 0
-    Not(Box<Expr>),              // 3
-    Xor(Box<Expr>, Box<Expr>),   // 0
-    Dot(Box<Expr>, Box<Expr>),   // 6
-    Index(Box<Expr>, Box<Expr>), // 6
+    Add(Box<Expr>, Box<Expr>, Span),      // 0
+    Sub(Box<Expr>, Box<Expr>, Span),      // 0
+    Mul(Box<Expr>, Box<Expr>, Span),      // 1
+    Div(Box<Expr>, Box<Expr>, Span),      // 1
+    FloorDiv(Box<Expr>, Box<Expr>, Span), // 1
+    Rem(Box<Expr>, Box<Expr>, Span),      // 1
+    Pow(Box<Expr>, Box<Expr>, Span),      // 4
+    Neg(Box<Expr>, Span),                 // 3
+    And(Box<Expr>, Box<Expr>, Span),      // 0
+    Or(Box<Expr>, Box<Expr>, Span),       // 0
+    Not(Box<Expr>, Span),                 // 3
+    Xor(Box<Expr>, Box<Expr>, Span),      // 0
+    Dot(Box<Expr>, Box<Expr>, Span),      // 6
+    Index(Box<Expr>, Box<Expr>, Span),    // 6
     Assign(String, Box<Expr>),
-    TableAssign(Box<Expr>, Box<Expr>),
+    LocalAssign(String, usize, Box<Expr>),
+    UpvalueAssign(String, usize, Box<Expr>),
+    Let(String, usize, Box<Expr>),
+    TableAssign(Box<Expr>, Box<Expr>, Span),
+    // 解构赋值：按位置赋值的目标、接收剩余值的目标、被解构的值、是否严格检查
+    Unpack(Vec<Box<Expr>>, Option<Box<Expr>>, Box<Expr>, bool, Span),
+    // 按键解构赋值：键名和目标、被解构的值、是否严格检查
+    UnpackKeys(Vec<(String, Box<Expr>)>, Box<Expr>, bool, Span),
+    // 多个值依次赋值给多个目标
+    MultiAssign(Vec<Box<Expr>>, Vec<Box<Expr>>),
     TableCreate(Vec<Box<Expr>>),
-    PairCreate(String, Box<Expr>),
-    Call(Box<Expr>, Vec<Box<Expr>>),    // 5
-    DotCall(Box<Expr>, Vec<Box<Expr>>), // 5
+    // 键值对：键和值，只能出现在 `Table` 字面量中
+    PairCreate(Box<Expr>, Box<Expr>),
+    Call(Box<Expr>, Vec<Box<Expr>>, Span),    // 5
+    DotCall(Box<Expr>, Vec<Box<Expr>>, Span), // 5
     Constant(SunValue),
     Variable(String),
+    Local(String, usize),
+    Upvalue(String, usize),
     // condition
-    Eq(Box<Expr>, Box<Expr>),
-    NotEq(Box<Expr>, Box<Expr>),
-    Le(Box<Expr>, Box<Expr>),
-    Ge(Box<Expr>, Box<Expr>),
-    Less(Box<Expr>, Box<Expr>),
-    Greater(Box<Expr>, Box<Expr>),
+    Eq(Box<Expr>, Box<Expr>, Span),
+    NotEq(Box<Expr>, Box<Expr>, Span),
+    Le(Box<Expr>, Box<Expr>, Span),
+    Ge(Box<Expr>, Box<Expr>, Span),
+    Less(Box<Expr>, Box<Expr>, Span),
+    Greater(Box<Expr>, Box<Expr>, Span),
     // if loop
     If(Box<Expr>, Vec<Box<Expr>>, Option<Vec<Box<Expr>>>),
+    // if 表达式和 `cond ? a : b`，分支最后的值作为表达式的值
+    IfExpr(Box<Expr>, Vec<Box<Expr>>, Vec<Box<Expr>>),
+    // match，被匹配的值保存在局部变量槽位中
+    Match(Box<Expr>, usize, Vec<(Vec<Pattern>, Vec<Box<Expr>>)>),
     Loop(Box<Expr>, Vec<Box<Expr>>),
+    For(
+        Vec<usize>,
+        Box<Expr>,
+        Option<Box<Expr>>,
+        Vec<Box<Expr>>,
+        Span,
+    ),
+    Break,
+    Continue,
+    Return(Box<Expr>),
+    // try catch
+    Try(Vec<Box<Expr>>, usize, Vec<Box<Expr>>),
     // function class
-    // DefFunction(String, Option<Vec<String>>, Vec<Box<Expr>>),
-    // Class(String, Option<Vec<String>>, Option<Vec<Box<Expr>>>),
+    DefFunction(String, Vec<String>, Vec<Box<Expr>>, usize, Vec<Capture>),
+    // 类定义：类名、基类名、方法名和方法、构造函数
+    DefClass(String, Option<String>, Vec<(String, Box<Expr>)>, Box<Expr>),
+    // 新建类的空实例
+    NewInstance(String),
     // import
     Import(String),
     // MetaCall
     MetaCall(String, String),
 }
 
+/// match 语句的模式
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `_`，匹配任意值
+    Wildcard,
+    /// 字面量，使用 `eq` 比较
+    Value(SunValue),
+    /// `start..end`，不含 `end`
+    Range(SunValue, SunValue),
+    /// `{"key": pattern}`，键对应的值保存在局部变量槽位中，再匹配子模式
+    Table(Vec<(String, usize, Pattern)>),
+}
+
 /// 语法树处理中间层
 #[derive(Debug)]
 pub enum Desc {
     Single(String),
     Double(String),
-    Dot,
+    CallMethod(usize),
+    Attr,
+    SetAttr,
     Index,
     Assign(String),
+    LocalAssign(usize),
+    UpvalueAssign(usize),
+    NewLocal(usize),
     TableAssign,
+    SetIndex,
     TableCreate(usize),
-    PairCreate(String),
+    PairCreate,
     Call(usize),
     Constant(SunValue),
     Variable(String),
+    Local(usize),
+    Upvalue(usize),
     If,
     IfTrueEnd,
     IfFalse,
     IfEnd,
+    BlockStart,
+    BlockValue,
+    BlockEnd,
+    Match,
+    Switch(Vec<Vec<TableKey>>),
+    MatchTest,
+    MatchTable(Vec<String>),
+    MatchRange(SunValue, SunValue),
+    MatchAlt,
+    MatchBody,
+    MatchArmEnd,
+    MatchEnd,
     Loop,
     LoopStart,
     LoopEnd,
-    // DefFunction(String, Option<Vec<String>>, usize),
+    Iter,
+    IterRange,
+    IterNext,
+    IterEnd,
+    Unpack(usize),
+    UnpackArray(usize, bool, bool),
+    UnpackKeys(Vec<String>, bool),
+    Break,
+    Continue,
+    Return,
+    Try,
+    TryEnd,
+    Catch(usize),
+    CatchEnd,
+    DefFunction(UserFunction),
+    Closure(Vec<Capture>),
+    DefClass(String, Option<String>, Vec<String>),
+    NewInstance(String),
     Import(String),
     MetaCall(String, String),
 }
 
-/// 将语法树翻译成虚拟机指令
-pub fn trans(ast: Box<Expr>, check: bool) -> Vec<Command> {
-    let mut expr_stack: Vec<Desc> = Vec::new();
-    traverse_expr(&mut expr_stack, &ast);
-    // println!("{:?}", expr_stack);
-    let mut commands: Vec<Command> = Vec::new();
-    for (position, desc) in expr_stack.iter().enumerate() {
+/// 流程控制语句中等待回填的跳转指令
+enum Block {
+    /// if 语句：条件不成立时的跳转和 then 分支结束时的跳转
+    If {
+        test: Option<usize>,
+        exit: Option<usize>,
+    },
+    /// 循环语句：循环开始的位置和所有跳出循环的跳转
+    Loop { start: usize, exits: Vec<usize> },
+    /// try 语句：`Try` 指令和 try 语句块结束时的跳转
+    Try { start: usize, exit: Option<usize> },
+    /// 有值的语句块：没有需要回填的跳转
+    Value,
+    /// match 语句
+    Match(MatchJumps),
+}
+
+/// match 语句中等待回填的跳转
+#[derive(Default)]
+struct MatchJumps {
+    /// 跳转表 `Switch` 指令的位置
+    switch: Option<usize>,
+    /// 各分支在跳转表中的键
+    keys: Vec<Vec<TableKey>>,
+    /// 当前分支的序号
+    arm: usize,
+    /// 当前模式匹配失败时的跳转
+    fails: Vec<usize>,
+    /// 模式匹配成功时跳到分支语句块的跳转
+    bodies: Vec<usize>,
+    /// 分支结束时跳出 match 语句的跳转
+    exits: Vec<usize>,
+}
+
+/// 指令生成器，跳转指令先以占位偏移生成，跳转目标生成时回填偏移
+struct Emitter {
+    commands: Vec<Command>,
+    blocks: Vec<Block>,
+}
+
+impl Emitter {
+    /// 生成指令，返回指令的位置
+    fn emit(&mut self, command: Command) -> usize {
+        self.commands.push(command);
+        self.commands.len() - 1
+    }
+
+    /// 回填位置 `index` 处的跳转指令，跳转到下一条生成的指令
+    fn patch(&mut self, index: usize) {
+        let offset = self.commands.len() - index - 1;
+        match &mut self.commands[index] {
+            Command::Jump(jump)
+            | Command::TestJump(jump)
+            | Command::Try(jump)
+            | Command::IterNext(jump)
+            | Command::MatchTable(_, jump)
+            | Command::MatchRange(_, _, jump) => *jump = offset,
+            other => unreachable!("patch non-jump command `{other:?}`"),
+        }
+    }
+
+    /// 生成跳回位置 `start` 的反向跳转
+    fn back(&mut self, start: usize) {
+        let offset = self.commands.len() + 1 - start;
+        self.emit(Command::Back(offset));
+    }
+
+    /// 最内层循环开始的位置和跳出循环的跳转
+    fn innermost_loop(&mut self) -> (usize, &mut Vec<usize>) {
+        self.blocks
+            .iter_mut()
+            .rev()
+            .find_map(|block| match block {
+                Block::Loop { start, exits } => Some((*start, exits)),
+                _ => None,
+            })
+            .expect("jump outside of loop")
+    }
+
+    /// 最内层的 match 语句
+    fn innermost_match(&mut self) -> &mut MatchJumps {
+        match self.blocks.last_mut() {
+            Some(Block::Match(jumps)) => jumps,
+            _ => unreachable!("pattern outside of match"),
+        }
+    }
+
+    /// 回填当前模式匹配失败时的跳转
+    fn patch_fails(&mut self) {
+        for fail in std::mem::take(&mut self.innermost_match().fails) {
+            self.patch(fail);
+        }
+    }
+
+    /// 跳出循环前离开循环中所有有值的语句块
+    fn exit_values(&mut self) {
+        let values = self
+            .blocks
+            .iter()
+            .rev()
+            .take_while(|block| !matches!(block, Block::Loop { .. }))
+            .filter(|block| matches!(block, Block::Value))
+            .count();
+        for _ in 0..values {
+            self.emit(Command::BlockExit);
+        }
+    }
+}
+
+/// 将语法树翻译成虚拟机指令 `span`: 语句的开始位置，用于没有记录位置的节点
+pub fn trans(ast: Box<Expr>, span: &Span, check: bool) -> Vec<(Command, Span)> {
+    let mut expr_stack: Vec<(Desc, Span)> = Vec::new();
+    traverse_expr(&mut expr_stack, &ast, span);
+    let mut emitter = Emitter {
+        commands: Vec::new(),
+        blocks: Vec::new(),
+    };
+    let mut spans: Vec<Span> = Vec::new();
+    for (desc, span) in expr_stack {
         match desc {
             Desc::Single(f) => {
-                commands.push(Command::LoadMethod(f.to_owned()));
-                commands.push(Command::Call(1));
+                emitter.emit(Command::LoadMethod(f));
+                emitter.emit(Command::Call(1));
             }
             Desc::Double(f) => {
-                commands.push(Command::LoadMethod(f.to_owned()));
-                commands.push(Command::Call(2));
+                emitter.emit(Command::LoadMethod(f));
+                emitter.emit(Command::Call(2));
             }
             Desc::Call(n) => {
-                commands.push(Command::Call(*n));
+                emitter.emit(Command::Call(n));
+            }
+            Desc::CallMethod(n) => {
+                emitter.emit(Command::CallMethod(n));
+            }
+            Desc::Attr => {
+                emitter.emit(Command::LoadAttr);
+            }
+            Desc::SetAttr => {
+                emitter.emit(Command::SetAttr);
             }
-            Desc::Dot => commands.push(Command::LoadMethod("dot".to_string())),
             Desc::Index => {
-                commands.push(Command::LoadMethod("index".to_string()));
-                commands.push(Command::Call(2));
-            }
-            Desc::Variable(v) => commands.push(Command::LoadValue(v.to_owned())),
-            Desc::Constant(c) => commands.push(Command::LoadConst(c.to_owned())),
-            Desc::Assign(n) => commands.push(Command::StoreGlobal(n.to_owned())),
-            Desc::TableAssign => commands.push(Command::SetTable),
-            Desc::TableCreate(n) => commands.push(Command::CreateTable(n.to_owned())),
-            Desc::PairCreate(k) => commands.push(Command::SetPair(k.to_owned())),
+                emitter.emit(Command::LoadMethod("index".to_string()));
+                emitter.emit(Command::Call(2));
+            }
+            Desc::Variable(v) => {
+                emitter.emit(Command::LoadValue(v));
+            }
+            Desc::Constant(c) => {
+                emitter.emit(Command::LoadConst(c));
+            }
+            Desc::Local(slot) => {
+                emitter.emit(Command::LoadLocal(slot));
+            }
+            Desc::Assign(n) => {
+                emitter.emit(Command::StoreGlobal(n));
+            }
+            Desc::LocalAssign(slot) => {
+                emitter.emit(Command::StoreLocal(slot));
+            }
+            Desc::Upvalue(index) => {
+                emitter.emit(Command::LoadUpvalue(index));
+            }
+            Desc::UpvalueAssign(index) => {
+                emitter.emit(Command::StoreUpvalue(index));
+            }
+            Desc::NewLocal(slot) => {
+                emitter.emit(Command::NewLocal(slot));
+            }
+            Desc::TableAssign => {
+                emitter.emit(Command::SetTable);
+            }
+            Desc::SetIndex => {
+                emitter.emit(Command::SetIndex);
+            }
+            Desc::TableCreate(n) => {
+                emitter.emit(Command::CreateTable(n));
+            }
+            Desc::PairCreate => {
+                emitter.emit(Command::SetPair);
+            }
             Desc::If => {
-                let if_false_pos = count_desc_distance(&expr_stack, position, &Desc::IfFalse);
-                let if_end_pos = count_desc_distance(&expr_stack, position, &Desc::IfEnd).unwrap();
-                match if_false_pos {
-                    Some(pos) => commands.push(Command::TestJump(pos - 1)),
-                    None => commands.push(Command::TestJump(if_end_pos - 1)),
-                }
+                let test = emitter.emit(Command::TestJump(0));
+                emitter.blocks.push(Block::If {
+                    test: Some(test),
+                    exit: None,
+                });
             }
             Desc::IfTrueEnd => {
-                let if_end_pos = count_desc_distance(&expr_stack, position, &Desc::IfEnd).unwrap();
-                commands.push(Command::Jump(if_end_pos - 1));
+                let jump = emitter.emit(Command::Jump(0));
+                if let Some(Block::If { exit, .. }) = emitter.blocks.last_mut() {
+                    *exit = Some(jump);
+                }
+            }
+            Desc::IfFalse => {
+                let test = match emitter.blocks.last_mut() {
+                    Some(Block::If { test, .. }) => test.take(),
+                    _ => None,
+                };
+                if let Some(test) = test {
+                    emitter.patch(test);
+                }
+            }
+            Desc::IfEnd => {
+                if let Some(Block::If { test, exit }) = emitter.blocks.pop() {
+                    // 没有 else 分支时，条件不成立直接跳到 if 语句之后
+                    for jump in test.into_iter().chain(exit) {
+                        emitter.patch(jump);
+                    }
+                }
+            }
+            Desc::BlockStart => {
+                emitter.emit(Command::BlockStart);
+                emitter.blocks.push(Block::Value);
+            }
+            Desc::BlockValue => {
+                emitter.emit(Command::BlockValue);
+            }
+            Desc::BlockEnd => {
+                emitter.blocks.pop();
+            }
+            Desc::Match => {
+                emitter.blocks.push(Block::Match(MatchJumps::default()));
+            }
+            Desc::Switch(keys) => {
+                let switch = emitter.emit(Command::Switch(HashMap::new()));
+                let jumps = emitter.innermost_match();
+                jumps.switch = Some(switch);
+                jumps.keys = keys;
+            }
+            Desc::MatchTest => {
+                let test = emitter.emit(Command::TestJump(0));
+                emitter.innermost_match().fails.push(test);
+            }
+            Desc::MatchTable(keys) => {
+                let test = emitter.emit(Command::MatchTable(keys, 0));
+                emitter.innermost_match().fails.push(test);
+            }
+            Desc::MatchRange(start, end) => {
+                let test = emitter.emit(Command::MatchRange(start, end, 0));
+                emitter.innermost_match().fails.push(test);
+            }
+            Desc::MatchAlt => {
+                // 前一个模式匹配成功时跳到分支语句块，失败时尝试下一个模式
+                let jump = emitter.emit(Command::Jump(0));
+                emitter.innermost_match().bodies.push(jump);
+                emitter.patch_fails();
+            }
+            Desc::MatchBody => {
+                let jumps = emitter.innermost_match();
+                let bodies = std::mem::take(&mut jumps.bodies);
+                let keys = jumps.keys.get_mut(jumps.arm).map(std::mem::take);
+                let switch = jumps.switch;
+                jumps.arm += 1;
+                for jump in bodies {
+                    emitter.patch(jump);
+                }
+                if let (Some(switch), Some(keys)) = (switch, keys) {
+                    let offset = emitter.commands.len() - switch - 1;
+                    if let Command::Switch(table) = &mut emitter.commands[switch] {
+                        // 重复的键跳到第一个分支
+                        for key in keys {
+                            table.entry(key).or_insert(offset);
+                        }
+                    }
+                }
+            }
+            Desc::MatchArmEnd => {
+                let exit = emitter.emit(Command::Jump(0));
+                emitter.innermost_match().exits.push(exit);
+                emitter.patch_fails();
+            }
+            Desc::MatchEnd => {
+                if let Some(Block::Match(jumps)) = emitter.blocks.pop() {
+                    for exit in jumps.exits {
+                        emitter.patch(exit);
+                    }
+                }
+            }
+            Desc::Loop => {
+                let start = emitter.commands.len();
+                emitter.blocks.push(Block::Loop {
+                    start,
+                    exits: Vec::new(),
+                });
             }
-            Desc::IfFalse => continue,
-            Desc::IfEnd => continue,
-            Desc::Loop => continue,
             Desc::LoopStart => {
-                let loop_end_pos =
-                    count_desc_distance(&expr_stack, position, &Desc::LoopEnd).unwrap();
-                commands.push(Command::TestJump(loop_end_pos + 1));
+                let test = emitter.emit(Command::TestJump(0));
+                emitter.innermost_loop().1.push(test);
             }
             Desc::LoopEnd => {
-                let loop_pos =
-                    reverse_count_desc_distance(&expr_stack, position, &Desc::Loop).unwrap();
-                commands.push(Command::Back(loop_pos));
+                if let Some(Block::Loop { start, exits }) = emitter.blocks.pop() {
+                    emitter.back(start);
+                    for exit in exits {
+                        emitter.patch(exit);
+                    }
+                }
+            }
+            Desc::Iter => {
+                emitter.emit(Command::Iter);
+            }
+            Desc::IterRange => {
+                emitter.emit(Command::IterRange);
+            }
+            Desc::IterNext => {
+                let next = emitter.emit(Command::IterNext(0));
+                emitter.innermost_loop().1.push(next);
+            }
+            Desc::IterEnd => {
+                emitter.emit(Command::IterEnd);
+            }
+            Desc::Unpack(n) => {
+                emitter.emit(Command::Unpack(n));
+            }
+            Desc::UnpackArray(n, rest, strict) => {
+                emitter.emit(Command::UnpackArray(n, rest, strict));
+            }
+            Desc::UnpackKeys(keys, strict) => {
+                emitter.emit(Command::UnpackKeys(keys, strict));
+            }
+            Desc::Break => {
+                emitter.exit_values();
+                let jump = emitter.emit(Command::Jump(0));
+                emitter.innermost_loop().1.push(jump);
+            }
+            Desc::Continue => {
+                emitter.exit_values();
+                let start = emitter.innermost_loop().0;
+                emitter.back(start);
+            }
+            Desc::Return => {
+                emitter.emit(Command::Return);
+            }
+            Desc::Try => {
+                let start = emitter.emit(Command::Try(0));
+                emitter.blocks.push(Block::Try { start, exit: None });
+            }
+            Desc::TryEnd => {
+                emitter.emit(Command::EndTry);
+                let jump = emitter.emit(Command::Jump(0));
+                if let Some(Block::Try { exit, .. }) = emitter.blocks.last_mut() {
+                    *exit = Some(jump);
+                }
+            }
+            Desc::Catch(slot) => {
+                // 出错时跳转到 catch 语句块的开始
+                if let Some(&Block::Try { start, .. }) = emitter.blocks.last() {
+                    emitter.patch(start);
+                }
+                emitter.emit(Command::NewLocal(slot));
+                emitter.emit(Command::StoreLocal(slot));
+            }
+            Desc::CatchEnd => {
+                if let Some(Block::Try {
+                    exit: Some(exit), ..
+                }) = emitter.blocks.pop()
+                {
+                    emitter.patch(exit);
+                }
+            }
+            Desc::DefFunction(function) => {
+                if check {
+                    debug_output(function.get_body(), false);
+                }
+                emitter.emit(Command::LoadConst(SunValue::from(function)));
+            }
+            Desc::Closure(captures) => {
+                emitter.emit(Command::MakeClosure(captures));
+            }
+            Desc::DefClass(name, base, methods) => {
+                emitter.emit(Command::DefineClass(name, base, methods));
+            }
+            Desc::NewInstance(name) => {
+                emitter.emit(Command::NewInstance(name));
+            }
+            Desc::Import(name) => {
+                emitter.emit(Command::Import(name));
+            }
+            Desc::MetaCall(name, method) => {
+                emitter.emit(Command::LoadMetamethod(name, method));
             }
-            Desc::Import(name) => commands.push(Command::Import(name.clone())),
-            Desc::MetaCall(name, method) => commands.push(Command::LoadMetamethod(
-                name.to_string(),
-                method.to_string(),
-            )),
         }
+        // 中间层节点生成的指令都使用该节点的位置
+        spans.resize(emitter.commands.len(), span);
     }
-    if check == true {
+    let commands: Vec<(Command, Span)> = emitter.commands.into_iter().zip(spans).collect();
+    if check {
         debug_output(&commands, false);
     }
     commands
 }
 
 /// 递归解析语法树，转换成中间层
-fn traverse_expr(expr_stack: &mut Vec<Desc>, expr: &Expr) {
+fn traverse_expr(expr_stack: &mut Vec<(Desc, Span)>, expr: &Expr, span: &Span) {
     match expr {
-        Expr::Add(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("add".to_string()));
-        }
-        Expr::Sub(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("sub".to_string()));
-        }
-        Expr::Mul(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("mul".to_string()));
-        }
-        Expr::Div(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("div".to_string()));
-        }
-        Expr::Rem(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("rem".to_string()));
-        }
-        Expr::And(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("and".to_string()));
-        }
-        Expr::Or(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("or".to_string()));
-        }
-        Expr::Xor(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("xor".to_string()));
-        }
-        Expr::Dot(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Dot);
-        }
-        Expr::Index(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Index);
-        }
-        Expr::Neg(left) => {
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Single("neg".to_string()));
-        }
-        Expr::Not(left) => {
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Single("not".to_string()));
-        }
-        Expr::Eq(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("eq".to_string()));
-        }
-        Expr::NotEq(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("noteq".to_string()));
-        }
-        Expr::Le(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("le".to_string()));
-        }
-        Expr::Ge(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("ge".to_string()));
-        }
-        Expr::Less(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("less".to_string()));
-        }
-        Expr::Greater(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::Double("greater".to_string()));
+        Expr::Add(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("add".to_string()), span.clone()));
+        }
+        Expr::Sub(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("sub".to_string()), span.clone()));
+        }
+        Expr::Mul(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("mul".to_string()), span.clone()));
+        }
+        Expr::Div(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("div".to_string()), span.clone()));
+        }
+        Expr::FloorDiv(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("floordiv".to_string()), span.clone()));
+        }
+        Expr::Rem(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("rem".to_string()), span.clone()));
+        }
+        Expr::Pow(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("pow".to_string()), span.clone()));
+        }
+        Expr::And(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("and".to_string()), span.clone()));
+        }
+        Expr::Or(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("or".to_string()), span.clone()));
+        }
+        Expr::Xor(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("xor".to_string()), span.clone()));
+        }
+        Expr::Dot(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Attr, span.clone()));
+        }
+        Expr::Index(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Index, span.clone()));
+        }
+        Expr::Neg(left, span) => {
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Single("neg".to_string()), span.clone()));
+        }
+        Expr::Not(left, span) => {
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Single("not".to_string()), span.clone()));
+        }
+        Expr::Eq(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("eq".to_string()), span.clone()));
+        }
+        Expr::NotEq(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("noteq".to_string()), span.clone()));
+        }
+        Expr::Le(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("le".to_string()), span.clone()));
+        }
+        Expr::Ge(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("ge".to_string()), span.clone()));
+        }
+        Expr::Less(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("less".to_string()), span.clone()));
+        }
+        Expr::Greater(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_expr(expr_stack, left, span);
+            expr_stack.push((Desc::Double("greater".to_string()), span.clone()));
         }
         Expr::Constant(value) => {
-            expr_stack.push(Desc::Constant(value.clone()));
+            expr_stack.push((Desc::Constant(value.clone()), span.clone()));
         }
-        Expr::Variable(name) => expr_stack.push(Desc::Variable(name.to_owned())),
+        Expr::Variable(name) => expr_stack.push((Desc::Variable(name.to_owned()), span.clone())),
+        Expr::Local(_, slot) => expr_stack.push((Desc::Local(*slot), span.clone())),
         Expr::Assign(name, expr) => {
-            traverse_expr(expr_stack, expr);
-            expr_stack.push(Desc::Assign(name.to_owned()));
+            traverse_expr(expr_stack, expr, span);
+            expr_stack.push((Desc::Assign(name.to_owned()), span.clone()));
+        }
+        Expr::LocalAssign(_, slot, expr) => {
+            traverse_expr(expr_stack, expr, span);
+            expr_stack.push((Desc::LocalAssign(*slot), span.clone()));
+        }
+        Expr::Upvalue(_, index) => expr_stack.push((Desc::Upvalue(*index), span.clone())),
+        Expr::UpvalueAssign(_, index, expr) => {
+            traverse_expr(expr_stack, expr, span);
+            expr_stack.push((Desc::UpvalueAssign(*index), span.clone()));
+        }
+        Expr::Let(_, slot, expr) => {
+            expr_stack.push((Desc::NewLocal(*slot), span.clone()));
+            traverse_expr(expr_stack, expr, span);
+            expr_stack.push((Desc::LocalAssign(*slot), span.clone()));
         }
-        Expr::TableAssign(left, right) => {
-            traverse_expr(expr_stack, right);
-            traverse_expr(expr_stack, left);
-            expr_stack.push(Desc::TableAssign);
+        Expr::TableAssign(left, right, span) => {
+            traverse_expr(expr_stack, right, span);
+            traverse_store(expr_stack, left, span);
         }
-        Expr::Call(name, args) => {
+        Expr::Call(name, args, span) => {
             for arg in args.iter().rev() {
-                traverse_expr(expr_stack, arg);
+                traverse_expr(expr_stack, arg, span);
             }
-            traverse_expr(expr_stack, name);
-            expr_stack.push(Desc::Call(args.len()));
+            traverse_expr(expr_stack, name, span);
+            expr_stack.push((Desc::Call(args.len()), span.clone()));
         }
-        Expr::DotCall(name, args) => {
+        Expr::DotCall(name, args, span) => {
             for arg in args.iter().rev() {
-                traverse_expr(expr_stack, arg);
+                traverse_expr(expr_stack, arg, span);
+            }
+            if let Expr::Dot(left, right, span) = &**name {
+                traverse_expr(expr_stack, right, span);
+                traverse_expr(expr_stack, left, span);
+            }
+            expr_stack.push((Desc::CallMethod(args.len()), span.clone()));
+        }
+        Expr::Unpack(targets, rest, value, strict, span) => {
+            traverse_expr(expr_stack, value, span);
+            let desc = Desc::UnpackArray(targets.len(), rest.is_some(), *strict);
+            expr_stack.push((desc, span.clone()));
+            for target in targets.iter().chain(rest) {
+                traverse_store(expr_stack, target, span);
+            }
+        }
+        Expr::UnpackKeys(entries, value, strict, span) => {
+            traverse_expr(expr_stack, value, span);
+            let keys = entries.iter().map(|(key, _)| key.clone()).collect();
+            expr_stack.push((Desc::UnpackKeys(keys, *strict), span.clone()));
+            for (_, target) in entries {
+                traverse_store(expr_stack, target, span);
+            }
+        }
+        Expr::MultiAssign(targets, values) => {
+            // 先计算所有的值，第一个值位于栈顶
+            for value in values.iter().rev() {
+                traverse_expr(expr_stack, value, span);
+            }
+            for target in targets {
+                traverse_store(expr_stack, target, span);
             }
-            traverse_expr(expr_stack, name);
-            expr_stack.push(Desc::Call(args.len() + 1));
         }
         Expr::TableCreate(values) => {
+            // 先用数组部分的值创建 `Table`，再依次写入键值对
+            let (pairs, values): (Vec<_>, Vec<_>) = values
+                .iter()
+                .partition(|value| matches!(***value, Expr::PairCreate(..)));
             for value in values.iter().rev() {
-                traverse_expr(expr_stack, value);
+                traverse_expr(expr_stack, value, span);
+            }
+            expr_stack.push((Desc::TableCreate(values.len()), span.clone()));
+            for pair in pairs {
+                traverse_expr(expr_stack, pair, span);
             }
-            expr_stack.push(Desc::TableCreate(values.len()));
         }
         Expr::PairCreate(key, value) => {
-            traverse_expr(expr_stack, value);
-            expr_stack.push(Desc::PairCreate(key.to_string()));
+            traverse_expr(expr_stack, value, span);
+            traverse_expr(expr_stack, key, span);
+            expr_stack.push((Desc::PairCreate, span.clone()));
         }
         Expr::If(cond, thens, elses) => {
-            traverse_expr(expr_stack, cond);
-            expr_stack.push(Desc::If);
+            traverse_expr(expr_stack, cond, span);
+            expr_stack.push((Desc::If, span.clone()));
             for then in thens {
-                traverse_expr(expr_stack, then);
+                traverse_expr(expr_stack, then, span);
             }
-            expr_stack.push(Desc::IfTrueEnd);
+            expr_stack.push((Desc::IfTrueEnd, span.clone()));
             if let Some(elses) = elses {
-                expr_stack.push(Desc::IfFalse);
+                expr_stack.push((Desc::IfFalse, span.clone()));
                 for else_ in elses {
-                    traverse_expr(expr_stack, else_);
+                    traverse_expr(expr_stack, else_, span);
+                }
+            }
+            expr_stack.push((Desc::IfEnd, span.clone()));
+        }
+        Expr::IfExpr(cond, thens, elses) => {
+            // 两个分支都以 `BlockValue` 结束，保证留下一个值
+            expr_stack.push((Desc::BlockStart, span.clone()));
+            traverse_expr(expr_stack, cond, span);
+            expr_stack.push((Desc::If, span.clone()));
+            for then in thens {
+                traverse_expr(expr_stack, then, span);
+            }
+            expr_stack.push((Desc::BlockValue, span.clone()));
+            expr_stack.push((Desc::IfTrueEnd, span.clone()));
+            expr_stack.push((Desc::IfFalse, span.clone()));
+            for else_ in elses {
+                traverse_expr(expr_stack, else_, span);
+            }
+            expr_stack.push((Desc::BlockValue, span.clone()));
+            expr_stack.push((Desc::IfEnd, span.clone()));
+            expr_stack.push((Desc::BlockEnd, span.clone()));
+        }
+        Expr::Match(value, slot, arms) => {
+            expr_stack.push((Desc::BlockStart, span.clone()));
+            traverse_expr(expr_stack, value, span);
+            expr_stack.push((Desc::NewLocal(*slot), span.clone()));
+            expr_stack.push((Desc::LocalAssign(*slot), span.clone()));
+            expr_stack.push((Desc::Match, span.clone()));
+            if let Some(keys) = switch_keys(arms) {
+                expr_stack.push((Desc::Local(*slot), span.clone()));
+                expr_stack.push((Desc::Switch(keys), span.clone()));
+            }
+            for (patterns, bodys) in arms {
+                for (i, pattern) in patterns.iter().enumerate() {
+                    if i > 0 {
+                        expr_stack.push((Desc::MatchAlt, span.clone()));
+                    }
+                    traverse_pattern(expr_stack, pattern, *slot, span);
+                }
+                expr_stack.push((Desc::MatchBody, span.clone()));
+                for body in bodys {
+                    traverse_expr(expr_stack, body, span);
                 }
+                expr_stack.push((Desc::BlockValue, span.clone()));
+                expr_stack.push((Desc::MatchArmEnd, span.clone()));
             }
-            expr_stack.push(Desc::IfEnd);
+            // 没有匹配的分支时值为 `Nil`
+            expr_stack.push((Desc::BlockValue, span.clone()));
+            expr_stack.push((Desc::MatchEnd, span.clone()));
+            expr_stack.push((Desc::BlockEnd, span.clone()));
         }
         Expr::Loop(cond, bodys) => {
-            expr_stack.push(Desc::Loop);
-            traverse_expr(expr_stack, cond);
-            expr_stack.push(Desc::LoopStart);
+            expr_stack.push((Desc::Loop, span.clone()));
+            traverse_expr(expr_stack, cond, span);
+            expr_stack.push((Desc::LoopStart, span.clone()));
             for body in bodys {
-                traverse_expr(expr_stack, body);
+                traverse_expr(expr_stack, body, span);
             }
-            expr_stack.push(Desc::LoopEnd);
+            expr_stack.push((Desc::LoopEnd, span.clone()));
+        }
+        Expr::For(slots, iter, end, bodys, span) => {
+            match end {
+                Some(end) => {
+                    traverse_expr(expr_stack, end, span);
+                    traverse_expr(expr_stack, iter, span);
+                    expr_stack.push((Desc::IterRange, span.clone()));
+                }
+                None => {
+                    traverse_expr(expr_stack, iter, span);
+                    expr_stack.push((Desc::Iter, span.clone()));
+                }
+            }
+            expr_stack.push((Desc::Loop, span.clone()));
+            expr_stack.push((Desc::IterNext, span.clone()));
+            if slots.len() > 1 {
+                expr_stack.push((Desc::Unpack(slots.len()), span.clone()));
+            }
+            for slot in slots {
+                expr_stack.push((Desc::NewLocal(*slot), span.clone()));
+                expr_stack.push((Desc::LocalAssign(*slot), span.clone()));
+            }
+            for body in bodys {
+                traverse_expr(expr_stack, body, span);
+            }
+            expr_stack.push((Desc::LoopEnd, span.clone()));
+            expr_stack.push((Desc::IterEnd, span.clone()));
+        }
+        Expr::Break => expr_stack.push((Desc::Break, span.clone())),
+        Expr::Continue => expr_stack.push((Desc::Continue, span.clone())),
+        Expr::Return(value) => {
+            traverse_expr(expr_stack, value, span);
+            expr_stack.push((Desc::Return, span.clone()));
         }
-        Expr::Import(name) => expr_stack.push(Desc::Import(name.clone())),
+        Expr::Try(bodys, slot, catches) => {
+            expr_stack.push((Desc::Try, span.clone()));
+            for body in bodys {
+                traverse_expr(expr_stack, body, span);
+            }
+            expr_stack.push((Desc::TryEnd, span.clone()));
+            expr_stack.push((Desc::Catch(*slot), span.clone()));
+            for catch in catches {
+                traverse_expr(expr_stack, catch, span);
+            }
+            expr_stack.push((Desc::CatchEnd, span.clone()));
+        }
+        Expr::DefFunction(name, params, bodys, frame_size, captures) => {
+            let mut body = Vec::new();
+            for expr in bodys {
+                body.append(&mut trans(expr.clone(), span, false));
+            }
+            let function = UserFunction::new(name, params.clone(), body, *frame_size);
+            expr_stack.push((Desc::DefFunction(function), span.clone()));
+            if !captures.is_empty() {
+                expr_stack.push((Desc::Closure(captures.clone()), span.clone()));
+            }
+        }
+        Expr::DefClass(name, base, methods, constructor) => {
+            for (_, method) in methods.iter().rev() {
+                traverse_expr(expr_stack, method, span);
+            }
+            traverse_expr(expr_stack, constructor, span);
+            let names = methods.iter().map(|(method, _)| method.clone()).collect();
+            let desc = Desc::DefClass(name.clone(), base.clone(), names);
+            expr_stack.push((desc, span.clone()));
+        }
+        Expr::NewInstance(name) => {
+            expr_stack.push((Desc::NewInstance(name.clone()), span.clone()));
+        }
+        Expr::Import(name) => expr_stack.push((Desc::Import(name.clone()), span.clone())),
         Expr::MetaCall(name, method) => {
-            expr_stack.push(Desc::MetaCall(name.clone(), method.clone()))
-        } // Expr::DefFunction(name, args) => {
-          //     for arg in args.iter().rev() {
-          //         traverse_expr(expr_stack, arg);
-          //     }
-          //     traverse_expr(expr_stack, name);
-          //     expr_stack.push(Desc::Call(args.len()));
-          // }
+            expr_stack.push((Desc::MetaCall(name.clone(), method.clone()), span.clone()))
+        }
     }
 }
 
-fn count_desc_distance(
-    expr_stack: &Vec<Desc>,
-    position: usize,
-    target_desc: &Desc,
-) -> Option<usize> {
-    let mut count = 0;
-    let mut is_found = false;
-    let p = expr_stack[position..].iter().position(|desc| match desc {
-        t if t == target_desc => {
-            is_found = true;
-            true
-        }
-        Desc::Single(_) | Desc::Double(_) | Desc::Index => {
-            if is_found == false {
-                count += 1
-            }
-            false
-        }
-        _ => false,
-    });
-    if let Some(p) = p {
-        Some(p + count)
-    } else {
-        None
+/// 生成将栈顶的值赋给 `target` 的指令
+fn traverse_store(expr_stack: &mut Vec<(Desc, Span)>, target: &Expr, span: &Span) {
+    match target {
+        Expr::Variable(name) => expr_stack.push((Desc::Assign(name.to_owned()), span.clone())),
+        Expr::Local(_, slot) => expr_stack.push((Desc::LocalAssign(*slot), span.clone())),
+        Expr::Upvalue(_, index) => expr_stack.push((Desc::UpvalueAssign(*index), span.clone())),
+        Expr::Index(table, key, span) => {
+            traverse_expr(expr_stack, key, span);
+            traverse_expr(expr_stack, table, span);
+            expr_stack.push((Desc::SetIndex, span.clone()));
+        }
+        Expr::Dot(object, attr, span) => {
+            traverse_expr(expr_stack, attr, span);
+            traverse_expr(expr_stack, object, span);
+            expr_stack.push((Desc::SetAttr, span.clone()));
+        }
+        target => {
+            traverse_expr(expr_stack, target, span);
+            expr_stack.push((Desc::TableAssign, span.clone()));
+        }
     }
 }
 
-fn reverse_count_desc_distance(
-    expr_stack: &[Desc],
-    position: usize,
-    target_desc: &Desc,
-) -> Option<usize> {
-    let mut count = 0;
-    let mut is_found = false;
-    let p = expr_stack[..=position].iter().rposition(|desc| match desc {
-        t if t == target_desc => {
-            is_found = true;
-            true
-        }
-        Desc::Single(_) | Desc::Double(_) | Desc::Index => {
-            if is_found == false {
-                count += 1
-            }
-            false
-        }
-        _ => false,
-    });
-    if let Some(mut p) = p {
-        p = position - p;
-        Some(p + count)
-    } else {
-        None
+/// 生成匹配局部变量 `slot` 中的值的指令，匹配失败时跳到下一个模式
+fn traverse_pattern(
+    expr_stack: &mut Vec<(Desc, Span)>,
+    pattern: &Pattern,
+    slot: usize,
+    span: &Span,
+) {
+    match pattern {
+        Pattern::Wildcard => {}
+        Pattern::Value(value) => {
+            // 调用字面量的 `eq` 方法进行比较，类型不同时为 `false`
+            expr_stack.push((Desc::Local(slot), span.clone()));
+            expr_stack.push((Desc::Constant(value.clone()), span.clone()));
+            expr_stack.push((Desc::Double("eq".to_string()), span.clone()));
+            expr_stack.push((Desc::MatchTest, span.clone()));
+        }
+        Pattern::Range(start, end) => {
+            expr_stack.push((Desc::Local(slot), span.clone()));
+            expr_stack.push((Desc::MatchRange(start.clone(), end.clone()), span.clone()));
+        }
+        Pattern::Table(entries) => {
+            expr_stack.push((Desc::Local(slot), span.clone()));
+            let keys = entries.iter().map(|(key, _, _)| key.clone()).collect();
+            expr_stack.push((Desc::MatchTable(keys), span.clone()));
+            for (_, slot, _) in entries {
+                expr_stack.push((Desc::NewLocal(*slot), span.clone()));
+                expr_stack.push((Desc::LocalAssign(*slot), span.clone()));
+            }
+            for (_, slot, pattern) in entries {
+                traverse_pattern(expr_stack, pattern, *slot, span);
+            }
+        }
     }
 }
 
-impl PartialEq for Desc {
-    fn eq(&self, other: &Self) -> bool {
-        use Desc::*;
-        match (self, other) {
-            (IfTrueEnd, IfTrueEnd) => true,
-            (IfFalse, IfFalse) => true,
-            (IfEnd, IfEnd) => true,
-            (Loop, Loop) => true,
-            (LoopEnd, LoopEnd) => true,
-            (LoopStart, LoopStart) => true,
-            _ => false,
-        }
+/// 各分支都只有可以放入跳转表的字面量时，返回各分支的键，`_` 之后的分支不会通过跳转表到达
+#[allow(clippy::vec_box)]
+fn switch_keys(arms: &[(Vec<Pattern>, Vec<Box<Expr>>)]) -> Option<Vec<Vec<TableKey>>> {
+    let mut keys = Vec::new();
+    for (patterns, _) in arms {
+        if patterns.iter().any(|p| matches!(p, Pattern::Wildcard)) {
+            break;
+        }
+        let arm = patterns
+            .iter()
+            .map(|p| match p {
+                Pattern::Value(value) => TableKey::from_value(value),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        keys.push(arm);
     }
+    keys.iter().any(|arm| !arm.is_empty()).then_some(keys)
 }
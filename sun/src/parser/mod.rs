@@ -1,2 +1,4 @@
 pub mod expr;
+#[allow(clippy::module_inception)]
 pub mod parser;
+pub mod scope;
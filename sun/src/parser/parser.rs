@@ -1,625 +1,1460 @@
 use crate::{
-    parser::expr::{trans, Expr},
+    parser::{
+        expr::{trans, Expr, Pattern},
+        scope::FuncScope,
+    },
     tokenizer::{token::Token, tokenizer::Tokenizer},
     vm::command::Command,
 };
 use std::io::Read;
+use std::rc::Rc;
 use sun_core::{
-    container::SunValue,
-    utils::{
-        err::SunError,
-        log::{debug_output, error_output},
-    },
+    container::{Capture, SunValue, TableKey},
+    utils::{err::SunError, log::debug_output, Span},
 };
 
 /// 语法分析器的结构体
 #[derive(Debug)]
 pub struct ParseProto<T: Read> {
-    /// 生成的指令序列
-    pub commands: Vec<Command>,
+    /// 生成的指令序列，每条指令附带源码位置
+    pub commands: Rc<Vec<(Command, Span)>>,
+    /// 顶层语段调用帧的大小
+    pub frame_size: usize,
     /// 词法分析器
     tokenizer: Tokenizer<T>,
     /// 检查语法树的标志
     check: bool,
     /// 检查生成命令的标志
     check_command: bool,
+    /// 当前所在循环的层数
+    loop_depth: usize,
+    /// 当前所在条件表达式 then 分支或 `Table` 字面量的键的层数，其中的 `:` 不作为元调用
+    ternary_depth: usize,
+    /// 作用域栈，第一层为顶层语段的作用域
+    scopes: Vec<FuncScope>,
 }
 
 impl<T: Read> ParseProto<T> {
-    /// 创建新的语法分析器
-    pub fn new(input: T, check_tokenizer: bool, check_parser: bool, check_command: bool) -> Self {
+    /// 创建新的语法分析器 `name`: 源文件名
+    pub fn new(
+        input: T,
+        name: &str,
+        check_tokenizer: bool,
+        check_parser: bool,
+        check_command: bool,
+    ) -> Result<Self, SunError> {
         let mut proto = ParseProto {
-            commands: Vec::new(),
-            tokenizer: Tokenizer::new(input, check_tokenizer),
+            commands: Rc::new(Vec::new()),
+            frame_size: 0,
+            tokenizer: Tokenizer::new(input, name, check_tokenizer),
             check: check_parser,
             check_command,
+            loop_depth: 0,
+            ternary_depth: 0,
+            scopes: vec![FuncScope::new()],
         };
-        proto.load();
-        proto
+        // 出错位置只由诊断信息给出，未附上诊断信息的错误指向最后读取的 `Token`
+        match proto.load() {
+            Ok(commands) => proto.commands = Rc::new(commands),
+            Err(e) => {
+                return Err(match e {
+                    e @ SunError::SourceError(..) => e,
+                    e => {
+                        let span = proto.tokenizer.span();
+                        proto.tokenizer.diagnose(e, span, None)
+                    }
+                })
+            }
+        }
+        proto.frame_size = proto.scopes[0].frame_size();
+        Ok(proto)
     }
 
-    /// 进行语法分析
-    fn load(&mut self) {
+    /// 进行语法分析，返回生成的指令序列
+    fn load(&mut self) -> Result<Vec<(Command, Span)>, SunError> {
+        let mut commands = Vec::new();
         loop {
-            let ast = self.parse_block();
+            let span = self.tokenizer.peek_span()?;
+            let ast = self.parse_block()?;
             if self.check {
                 debug_output(&ast, true);
             }
-            self.commands.append(&mut trans(ast, self.check_command));
-            match self.tokenizer.peek() {
-                &Token::Eos => break,
-                &Token::Semi => {
-                    self.tokenizer.next();
+            commands.append(&mut trans(ast, &span, self.check_command));
+            match *self.tokenizer.peek()? {
+                Token::Eos => break,
+                Token::Semi => {
+                    self.next()?;
                     continue;
                 }
-                _ => break,
+                ref other => {
+                    let e =
+                        SunError::SymbolError(format!("unexpected token `{:?}`", other.clone()));
+                    let span = self.tokenizer.peek_span()?;
+                    let help = "statements are separated by `;`";
+                    return Err(self.tokenizer.diagnose(e, span, Some(help)));
+                }
             }
         }
+        Ok(commands)
     }
 
     /// 语句段：定义语段
-    fn parse_chunk(&mut self) -> Vec<Box<Expr>> {
+    #[allow(clippy::vec_box)]
+    fn parse_chunk(&mut self) -> Result<Vec<Box<Expr>>, SunError> {
+        self.scope().enter_block();
         let mut blocks = Vec::new();
-        if matches!(self.tokenizer.peek(), Token::CurR) {
-            self.tokenizer.next();
+        if matches!(self.tokenizer.peek()?, Token::CurR) {
+            self.next()?;
         } else {
-            blocks.push(self.parse_block());
+            blocks.push(self.parse_block()?);
             loop {
-                match self.tokenizer.peek() {
+                match self.tokenizer.peek()? {
                     &Token::Semi => {
-                        self.tokenizer.next();
-                        blocks.push(self.parse_block());
+                        self.next()?;
+                        blocks.push(self.parse_block()?);
                         continue;
                     }
                     &Token::CurR => {
-                        self.tokenizer.next();
+                        self.next()?;
                         break;
                     }
                     other => {
                         let e = SunError::SymbolError(format!(
-                            "unexpected token `{:?}` at line {}",
-                            other.clone(),
-                            self.tokenizer.line()
+                            "unexpected token `{:?}`",
+                            other.clone()
                         ));
-                        error_output(e)
+                        let span = self.tokenizer.peek_span()?;
+                        let help = "statements in a block are separated by `;`";
+                        return Err(self.tokenizer.diagnose(e, span, Some(help)));
                     }
                 };
             }
         }
-        blocks
+        self.scope().leave_block();
+        Ok(blocks)
     }
 
     /// 语句块：流程控制语段或表达式
-    fn parse_block(&mut self) -> Box<Expr> {
-        match self.tokenizer.peek() {
+    fn parse_block(&mut self) -> Result<Box<Expr>, SunError> {
+        match self.tokenizer.peek()? {
             &Token::Import => self.parse_import(),
-            &Token::DefFunction => self.parse_def(),
-            &Token::If | &Token::Loop => self.parse_control(),
-            _ => self.parse_expr(),
+            &Token::DefFunction | &Token::DefClass => self.parse_def(),
+            &Token::If | &Token::Loop | &Token::For => self.parse_control(),
+            &Token::Return | &Token::Break | &Token::Continue => self.parse_jump(),
+            &Token::Let => self.parse_let(),
+            &Token::Try => self.parse_try(),
+            &Token::SquL => self.parse_unpack_array(),
+            _ => {
+                let expr = self.parse_expr()?;
+                if self.tokenizer.peek()? != &Token::Comma {
+                    return Ok(expr);
+                }
+                // `a, b = ...`
+                let mut targets = vec![expr];
+                let mut rest = None;
+                while self.tokenizer.peek()? == &Token::Comma {
+                    self.next()?;
+                    if !self.parse_target(&mut targets, &mut rest)? {
+                        break;
+                    }
+                }
+                self.parse_unpack(targets, rest)
+            }
         }
     }
 
-    /// 表达式语句
-    fn parse_expr(&mut self) -> Box<Expr> {
-        self.parse_logic()
+    /// 表达式语句：`cond ? a : b` 或 and or xor
+    fn parse_expr(&mut self) -> Result<Box<Expr>, SunError> {
+        let mut cond = self.parse_logic()?;
+        if self.tokenizer.peek()? != &Token::Question {
+            return Ok(cond);
+        }
+        self.unexpect_assign(&mut cond)?;
+        self.next()?;
+        self.ternary_depth += 1;
+        let then = self.parse_expr();
+        self.ternary_depth -= 1;
+        let mut then = then?;
+        self.unexpect_assign(&mut then)?;
+        self.expect(Token::Colon)?;
+        let mut else_ = self.parse_expr()?;
+        self.unexpect_assign(&mut else_)?;
+        Ok(Box::new(Expr::IfExpr(cond, vec![then], vec![else_])))
     }
 
     /// 流程控制语句
-    fn parse_control(&mut self) -> Box<Expr> {
-        match self.tokenizer.peek() {
-            &Token::If => self.parse_if(),
-            &Token::Loop => self.parse_loop(),
+    fn parse_control(&mut self) -> Result<Box<Expr>, SunError> {
+        match *self.tokenizer.peek()? {
+            Token::If => self.parse_if(),
+            Token::Loop => self.parse_loop(),
+            Token::For => self.parse_for(),
             _ => unreachable!("parse control"),
         }
     }
 
+    /// 跳转语句：返回、跳出循环和继续循环
+    fn parse_jump(&mut self) -> Result<Box<Expr>, SunError> {
+        match self.next()? {
+            Some(Token::Return) => {
+                if self.scopes.len() == 1 {
+                    let e = SunError::SymbolError("`->` outside of function".to_string());
+                    let span = self.tokenizer.span();
+                    let help = "`->` returns from a function and can only be used in its body";
+                    return Err(self.tokenizer.diagnose(e, span, Some(help)));
+                }
+                let value = match self.tokenizer.peek()? {
+                    Token::Semi | Token::CurR | Token::Eos => {
+                        Box::new(Expr::Constant(SunValue::Nil))
+                    }
+                    _ => self.parse_expr()?,
+                };
+                Ok(Box::new(Expr::Return(value)))
+            }
+            Some(token @ (Token::Break | Token::Continue)) => {
+                let keyword = match token {
+                    Token::Break => "break",
+                    _ => "continue",
+                };
+                if self.loop_depth == 0 {
+                    let e = SunError::SymbolError(format!("`{keyword}` outside of loop"));
+                    let span = self.tokenizer.span();
+                    return Err(self.tokenizer.diagnose(e, span, None));
+                }
+                match token {
+                    Token::Break => Ok(Box::new(Expr::Break)),
+                    _ => Ok(Box::new(Expr::Continue)),
+                }
+            }
+            _ => unreachable!("parse jump"),
+        }
+    }
+
+    /// 局部变量声明语句
+    fn parse_let(&mut self) -> Result<Box<Expr>, SunError> {
+        self.expect(Token::Let)?;
+        let name = match self.next()? {
+            Some(Token::Name(name)) => name,
+            other => {
+                let e =
+                    SunError::SymbolError(format!("expected variable name, but got `{:?}`", other));
+                let span = self.tokenizer.span();
+                return Err(self.tokenizer.diagnose(e, span, None));
+            }
+        };
+        let value = if self.tokenizer.peek()? == &Token::Assign {
+            self.next()?;
+            self.parse_expr()?
+        } else {
+            Box::new(Expr::Constant(SunValue::Nil))
+        };
+        let slot = self.declare(&name);
+        Ok(self.assign(name, slot, value))
+    }
+
+    /// 异常处理语句，catch 后的变量为 catch 语句块中的局部变量
+    fn parse_try(&mut self) -> Result<Box<Expr>, SunError> {
+        self.expect(Token::Try)?;
+        self.expect(Token::CurL)?;
+        let bodys = self.parse_chunk()?;
+        self.expect(Token::Catch)?;
+        let name = match self.next()? {
+            Some(Token::Name(name)) => name,
+            other => {
+                let e =
+                    SunError::SymbolError(format!("expected error name, but got `{:?}`", other));
+                let span = self.tokenizer.span();
+                return Err(self.tokenizer.diagnose(e, span, None));
+            }
+        };
+        self.expect(Token::CurL)?;
+        self.scope().enter_block();
+        let slot = self.scope().declare(&name);
+        let catches = self.parse_chunk()?;
+        self.scope().leave_block();
+        Ok(Box::new(Expr::Try(bodys, slot, catches)))
+    }
+
     /// 定义语句
-    fn parse_def(&mut self) -> Box<Expr> {
-        match self.tokenizer.peek() {
-            &Token::DefFunction => self.parse_deffunc(),
+    fn parse_def(&mut self) -> Result<Box<Expr>, SunError> {
+        match *self.tokenizer.peek()? {
+            Token::DefFunction => self.parse_deffunc(),
+            Token::DefClass => self.parse_defclass(),
             _ => unreachable!("parse def"),
         }
     }
 
-    /// 导入模块语句
-    fn parse_import(&mut self) -> Box<Expr> {
-        match self.tokenizer.next() {
-            Some(Token::Import) => match self.tokenizer.peek() {
-                &Token::String(ref lib_name) => {
-                    Box::new(Expr::Import(String::from_utf8_lossy(&lib_name).to_string()))
-                }
-                other => {
-                    let e =
-                        SunError::SymbolError(format!("expected lib name, but got `{:?}`", other));
-                    error_output(e);
-                }
-            },
-            _ => unreachable!("parse import"),
+    /// 导入模块语句，模块绑定到与文件名同名的变量
+    fn parse_import(&mut self) -> Result<Box<Expr>, SunError> {
+        let path = self.parse_module()?;
+        let name = std::path::Path::new(&path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let valid = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if !valid {
+            let e = SunError::SymbolError(format!("module `{path}` can't be bound to a variable"));
+            let span = self.tokenizer.span();
+            let help = "assign the module to a variable with `name = @\"...\"`";
+            return Err(self.tokenizer.diagnose(e, span, Some(help)));
+        }
+        let slot = self.declare(&name);
+        Ok(self.assign(name, slot, Box::new(Expr::Import(path))))
+    }
+
+    /// 模块路径 `@"path"`
+    fn parse_module(&mut self) -> Result<String, SunError> {
+        self.expect(Token::Import)?;
+        match self.next()? {
+            Some(Token::String(path)) => Ok(String::from_utf8_lossy(&path).to_string()),
+            other => {
+                let e =
+                    SunError::SymbolError(format!("expected module path, but got `{:?}`", other));
+                let span = self.tokenizer.span();
+                Err(self.tokenizer.diagnose(e, span, None))
+            }
         }
     }
 
     /// and or xor
-    fn parse_logic(&mut self) -> Box<Expr> {
-        let mut left = self.parse_compare();
+    fn parse_logic(&mut self) -> Result<Box<Expr>, SunError> {
+        let mut left = self.parse_compare()?;
         loop {
-            match self.tokenizer.peek() {
-                &Token::And => {
-                    self.tokenizer.next();
-                    let right = self.parse_compare();
-                    left = Box::new(Expr::And(left, right));
-                }
-                &Token::Or => {
-                    self.tokenizer.next();
-                    let right = self.parse_compare();
-                    left = Box::new(Expr::Or(left, right));
-                }
-                &Token::Xor => {
-                    self.tokenizer.next();
-                    let right = self.parse_compare();
-                    left = Box::new(Expr::Xor(left, right));
+            match *self.tokenizer.peek()? {
+                Token::And => {
+                    let span = self.next_span()?;
+                    let right = self.parse_compare()?;
+                    left = Box::new(Expr::And(left, right, span));
+                }
+                Token::Or => {
+                    let span = self.next_span()?;
+                    let right = self.parse_compare()?;
+                    left = Box::new(Expr::Or(left, right, span));
+                }
+                Token::Xor => {
+                    let span = self.next_span()?;
+                    let right = self.parse_compare()?;
+                    left = Box::new(Expr::Xor(left, right, span));
                 }
                 _ => break,
             }
         }
-        left
+        Ok(left)
     }
 
     /// compare
-    fn parse_compare(&mut self) -> Box<Expr> {
-        let left = self.parse_0();
-        match self.tokenizer.peek() {
-            &Token::Eq => {
-                self.tokenizer.next();
-                let right = self.parse_0();
-                Box::new(Expr::Eq(left, right))
+    fn parse_compare(&mut self) -> Result<Box<Expr>, SunError> {
+        let left = self.parse_0()?;
+        match *self.tokenizer.peek()? {
+            Token::Eq => {
+                let span = self.next_span()?;
+                let right = self.parse_0()?;
+                Ok(Box::new(Expr::Eq(left, right, span)))
             }
-            &Token::NotEq => {
-                self.tokenizer.next();
-                let right = self.parse_0();
-                Box::new(Expr::NotEq(left, right))
+            Token::NotEq => {
+                let span = self.next_span()?;
+                let right = self.parse_0()?;
+                Ok(Box::new(Expr::NotEq(left, right, span)))
             }
-            &Token::Le => {
-                self.tokenizer.next();
-                let right = self.parse_0();
-                Box::new(Expr::Le(left, right))
+            Token::Le => {
+                let span = self.next_span()?;
+                let right = self.parse_0()?;
+                Ok(Box::new(Expr::Le(left, right, span)))
             }
-            &Token::Ge => {
-                self.tokenizer.next();
-                let right = self.parse_0();
-                Box::new(Expr::Ge(left, right))
+            Token::Ge => {
+                let span = self.next_span()?;
+                let right = self.parse_0()?;
+                Ok(Box::new(Expr::Ge(left, right, span)))
             }
-            &Token::Less => {
-                self.tokenizer.next();
-                let right = self.parse_0();
-                Box::new(Expr::Less(left, right))
+            Token::Less => {
+                let span = self.next_span()?;
+                let right = self.parse_0()?;
+                Ok(Box::new(Expr::Less(left, right, span)))
             }
-            &Token::Greater => {
-                self.tokenizer.next();
-                let right = self.parse_0();
-                Box::new(Expr::Greater(left, right))
+            Token::Greater => {
+                let span = self.next_span()?;
+                let right = self.parse_0()?;
+                Ok(Box::new(Expr::Greater(left, right, span)))
             }
-            _ => left,
+            _ => Ok(left),
         }
     }
 
     /// add sub and or xor
-    fn parse_0(&mut self) -> Box<Expr> {
-        let mut left = self.parse_1();
+    fn parse_0(&mut self) -> Result<Box<Expr>, SunError> {
+        let mut left = self.parse_1()?;
         loop {
-            match self.tokenizer.peek() {
-                &Token::Add => {
-                    self.tokenizer.next();
-                    let right = self.parse_1();
-                    left = Box::new(Expr::Add(left, right));
+            match *self.tokenizer.peek()? {
+                Token::Add => {
+                    let span = self.next_span()?;
+                    let right = self.parse_1()?;
+                    left = Box::new(Expr::Add(left, right, span));
                 }
-                &Token::Sub => {
-                    self.tokenizer.next();
-                    let right = self.parse_1();
-                    left = Box::new(Expr::Sub(left, right));
+                Token::Sub => {
+                    let span = self.next_span()?;
+                    let right = self.parse_1()?;
+                    left = Box::new(Expr::Sub(left, right, span));
                 }
                 _ => break,
             }
         }
-        left
+        Ok(left)
     }
 
     /// mul div mod
-    fn parse_1(&mut self) -> Box<Expr> {
-        let mut left = self.parse_2();
+    fn parse_1(&mut self) -> Result<Box<Expr>, SunError> {
+        let mut left = self.parse_2()?;
         loop {
-            match self.tokenizer.peek() {
-                &Token::Mul => {
-                    self.tokenizer.next();
-                    let right = self.parse_2();
-                    left = Box::new(Expr::Mul(left, right));
-                }
-                &Token::Div => {
-                    self.tokenizer.next();
-                    let right = self.parse_2();
-                    left = Box::new(Expr::Div(left, right));
-                }
-                &Token::Mod => {
-                    self.tokenizer.next();
-                    let right = self.parse_2();
-                    left = Box::new(Expr::Rem(left, right));
+            match *self.tokenizer.peek()? {
+                Token::Mul => {
+                    let span = self.next_span()?;
+                    let right = self.parse_2()?;
+                    left = Box::new(Expr::Mul(left, right, span));
+                }
+                Token::Div => {
+                    let span = self.next_span()?;
+                    let right = self.parse_2()?;
+                    left = Box::new(Expr::Div(left, right, span));
+                }
+                Token::FloorDiv => {
+                    let span = self.next_span()?;
+                    let right = self.parse_2()?;
+                    left = Box::new(Expr::FloorDiv(left, right, span));
+                }
+                Token::Mod => {
+                    let span = self.next_span()?;
+                    let right = self.parse_2()?;
+                    left = Box::new(Expr::Rem(left, right, span));
                 }
                 _ => break,
             }
         }
-        left
+        Ok(left)
     }
 
     /// neg not
-    fn parse_2(&mut self) -> Box<Expr> {
-        match self.tokenizer.peek() {
-            &Token::Sub => {
-                self.tokenizer.next();
-                Box::new(Expr::Neg(self.parse_2()))
+    fn parse_2(&mut self) -> Result<Box<Expr>, SunError> {
+        match *self.tokenizer.peek()? {
+            Token::Sub => {
+                let span = self.next_span()?;
+                Ok(Box::new(Expr::Neg(self.parse_2()?, span)))
             }
-            &Token::Not => {
-                self.tokenizer.next();
-                Box::new(Expr::Not(self.parse_2()))
+            Token::Not => {
+                let span = self.next_span()?;
+                Ok(Box::new(Expr::Not(self.parse_2()?, span)))
             }
-            _ => self.parse_3(),
+            _ => self.parse_pow(),
         }
     }
 
-    /// function call and assign
-    fn parse_3(&mut self) -> Box<Expr> {
-        let name = self.parse_4();
-        match self.tokenizer.peek() {
-            &Token::ParL => {
-                let mut args = Vec::new();
-                self.tokenizer.next();
-                if self.tokenizer.peek() != &Token::ParR {
-                    args.push(self.parse_expr());
-                    while self.tokenizer.peek() == &Token::Comma {
-                        self.tokenizer.next();
-                        args.push(self.parse_expr());
-                    }
-                }
-                self.expect(Token::ParR);
-                match *name.clone() {
-                    Expr::Dot(_, _) => Box::new(Expr::DotCall(name, args)),
-                    _ => Box::new(Expr::Call(name, args)),
-                }
+    /// pow，右结合，优先级高于一元运算符：`-2 ^ 2` 为 `-4`，`2 ^ 3 ^ 2` 为 `2 ^ 9`
+    fn parse_pow(&mut self) -> Result<Box<Expr>, SunError> {
+        let left = self.parse_3()?;
+        if self.tokenizer.peek()? != &Token::Pow {
+            return Ok(left);
+        }
+        let span = self.next_span()?;
+        let right = self.parse_2()?;
+        Ok(Box::new(Expr::Pow(left, right, span)))
+    }
+
+    /// assign
+    fn parse_3(&mut self) -> Result<Box<Expr>, SunError> {
+        let name = self.parse_4()?;
+        match *self.tokenizer.peek()? {
+            Token::Assign
+            | Token::AddAssign
+            | Token::SubAssign
+            | Token::MulAssign
+            | Token::DivAssign
+            | Token::FloorDivAssign
+            | Token::ModAssign
+            | Token::PowAssign => self.parse_assign(name),
+            _ => Ok(name),
+        }
+    }
+
+    /// 赋值语句，复合赋值 `a op= b` 展开为 `a = a op b`
+    fn parse_assign(&mut self, target: Box<Expr>) -> Result<Box<Expr>, SunError> {
+        let op = self.next()?;
+        let span = self.tokenizer.span();
+        if let (Expr::TableCreate(items), Some(Token::Assign)) = (*target.clone(), &op) {
+            return self.parse_unpack_keys(items, span);
+        }
+        self.check_target(&target, &span)?;
+        let value = self.parse_expr()?;
+        let left = target.clone();
+        let value = match op {
+            Some(Token::AddAssign) => Box::new(Expr::Add(left, value, span.clone())),
+            Some(Token::SubAssign) => Box::new(Expr::Sub(left, value, span.clone())),
+            Some(Token::MulAssign) => Box::new(Expr::Mul(left, value, span.clone())),
+            Some(Token::DivAssign) => Box::new(Expr::Div(left, value, span.clone())),
+            Some(Token::FloorDivAssign) => Box::new(Expr::FloorDiv(left, value, span.clone())),
+            Some(Token::ModAssign) => Box::new(Expr::Rem(left, value, span.clone())),
+            Some(Token::PowAssign) => Box::new(Expr::Pow(left, value, span.clone())),
+            _ => value,
+        };
+        let expr = match *target {
+            Expr::Variable(n) => Expr::Assign(n, value),
+            Expr::Local(n, slot) => Expr::LocalAssign(n, slot, value),
+            Expr::Upvalue(n, index) => Expr::UpvalueAssign(n, index, value),
+            ta => Expr::TableAssign(Box::new(ta), value, span),
+        };
+        Ok(Box::new(expr))
+    }
+
+    /// 检查赋值的目标是否为变量、`t[k]` 或 `t.k`
+    fn check_target(&mut self, target: &Expr, span: &Span) -> Result<(), SunError> {
+        if !matches!(
+            target,
+            Expr::Variable(_)
+                | Expr::Local(..)
+                | Expr::Upvalue(..)
+                | Expr::Index(..)
+                | Expr::Dot(..)
+        ) {
+            let e = SunError::AssignError("invalid assigment statement".to_string());
+            let help = "only variables, `t[k]` and `t.k` can be assigned to";
+            return Err(self.tokenizer.diagnose(e, span.clone(), Some(help)));
+        }
+        Ok(())
+    }
+
+    /// 解构赋值的一个目标，`...name` 接收剩余的值且必须是最后一个目标，返回是否可以继续
+    #[allow(clippy::vec_box)]
+    fn parse_target(
+        &mut self,
+        targets: &mut Vec<Box<Expr>>,
+        rest: &mut Option<Box<Expr>>,
+    ) -> Result<bool, SunError> {
+        if self.tokenizer.peek()? == &Token::Ellipsis {
+            self.next()?;
+            *rest = Some(self.parse_4()?);
+            return Ok(false);
+        }
+        targets.push(self.parse_4()?);
+        Ok(true)
+    }
+
+    /// `[a, b, ...rest] = value`
+    fn parse_unpack_array(&mut self) -> Result<Box<Expr>, SunError> {
+        self.expect(Token::SquL)?;
+        let mut targets = Vec::new();
+        let mut rest = None;
+        while self.tokenizer.peek()? != &Token::SquR {
+            if !self.parse_target(&mut targets, &mut rest)?
+                || self.tokenizer.peek()? != &Token::Comma
+            {
+                break;
             }
-            &Token::Assign => {
-                self.tokenizer.next();
-                match *name {
-                    Expr::Variable(n) => Box::new(Expr::Assign(n, self.parse_expr())),
-                    ta @ (Expr::Index(_, _) | Expr::Dot(_, _)) => {
-                        Box::new(Expr::TableAssign(Box::new(ta), self.parse_expr()))
-                    }
-                    _ => {
-                        let e = SunError::AssignError(format!(
-                            "invalid assigment statement at line {}",
-                            self.tokenizer.line()
-                        ));
-                        error_output(e)
-                    }
-                }
+            self.next()?;
+        }
+        self.expect(Token::SquR)?;
+        self.parse_unpack(targets, rest)
+    }
+
+    /// 解构赋值的值，单个值在运行时解构，以 `!` 结尾时严格检查值的个数；
+    /// 多个值在编译时依次对应到各目标
+    #[allow(clippy::vec_box)]
+    fn parse_unpack(
+        &mut self,
+        mut targets: Vec<Box<Expr>>,
+        rest: Option<Box<Expr>>,
+    ) -> Result<Box<Expr>, SunError> {
+        let span = self.tokenizer.peek_span()?;
+        for target in targets.iter().chain(&rest) {
+            self.check_target(target, &span)?;
+        }
+        self.expect(Token::Assign)?;
+        let mut values = vec![self.parse_expr()?];
+        while self.tokenizer.peek()? == &Token::Comma {
+            self.next()?;
+            values.push(self.parse_expr()?);
+        }
+        if values.len() == 1 {
+            let strict = self.tokenizer.peek()? == &Token::Not;
+            if strict {
+                self.next()?;
             }
-            _ => name,
+            let value = values.pop().unwrap();
+            return Ok(Box::new(Expr::Unpack(targets, rest, value, strict, span)));
+        }
+        if values.len() > targets.len() && rest.is_none() {
+            let e = SunError::AssignError("too many values to unpack".to_string());
+            let help = "use `...name` to collect the remaining values";
+            return Err(self.tokenizer.diagnose(e, span, Some(help)));
+        }
+        let others = values.split_off(targets.len().min(values.len()));
+        values.resize_with(targets.len(), || Box::new(Expr::Constant(SunValue::Nil)));
+        if let Some(rest) = rest {
+            targets.push(rest);
+            values.push(Box::new(Expr::TableCreate(others)));
         }
+        Ok(Box::new(Expr::MultiAssign(targets, values)))
     }
 
-    /// dot index
-    fn parse_4(&mut self) -> Box<Expr> {
-        let mut left = self.parse_metacall();
+    /// `{x, "key": y} = value`，变量名作为键名，以 `!` 结尾时严格检查键是否存在
+    #[allow(clippy::vec_box)]
+    fn parse_unpack_keys(
+        &mut self,
+        items: Vec<Box<Expr>>,
+        span: Span,
+    ) -> Result<Box<Expr>, SunError> {
+        let mut entries = Vec::new();
+        for item in items {
+            let entry = match *item {
+                Expr::Variable(ref name)
+                | Expr::Local(ref name, _)
+                | Expr::Upvalue(ref name, _) => (name.clone(), item),
+                Expr::PairCreate(key, target)
+                    if matches!(*key, Expr::Constant(SunValue::String(_))) =>
+                {
+                    let Expr::Constant(key) = *key else {
+                        unreachable!("string key")
+                    };
+                    (String::try_from(&key)?, target)
+                }
+                _ => {
+                    let e = SunError::AssignError("invalid unpack target".to_string());
+                    let help = "use `{name}` or `{\"key\": target}` to unpack a `Table`";
+                    return Err(self.tokenizer.diagnose(e, span, Some(help)));
+                }
+            };
+            self.check_target(&entry.1, &span)?;
+            entries.push(entry);
+        }
+        let value = self.parse_expr()?;
+        let strict = self.tokenizer.peek()? == &Token::Not;
+        if strict {
+            self.next()?;
+        }
+        Ok(Box::new(Expr::UnpackKeys(entries, value, strict, span)))
+    }
+
+    /// dot, index and function call，可以连续使用：`f().x`、`f()[0]`、`B().get()`
+    fn parse_4(&mut self) -> Result<Box<Expr>, SunError> {
+        let mut left = self.parse_metacall()?;
         loop {
-            match self.tokenizer.peek() {
-                &Token::Dot => {
-                    self.tokenizer.next();
-                    match self.tokenizer.peek() {
-                        &Token::Name(ref name) => {
-                            let name = name.clone();
-                            self.tokenizer.next();
-                            let right = Box::new(Expr::Constant(SunValue::from(name)));
-                            left = Box::new(Expr::Dot(left, right));
-                        }
-                        _ => {
-                            let e = SunError::AttributeError(format!(
-                                "invalid get attribute statement because of invalid token at line {}",
-                                self.tokenizer.line()
-                            ));
-                            error_output(e)
+            match *self.tokenizer.peek()? {
+                Token::ParL => {
+                    let mut args = Vec::new();
+                    let span = self.next_span()?;
+                    if self.tokenizer.peek()? != &Token::ParR {
+                        args.push(self.parse_expr()?);
+                        while self.tokenizer.peek()? == &Token::Comma {
+                            self.next()?;
+                            args.push(self.parse_expr()?);
                         }
                     }
+                    self.expect(Token::ParR)?;
+                    left = match *left {
+                        Expr::Dot(..) => Box::new(Expr::DotCall(left, args, span)),
+                        _ => Box::new(Expr::Call(left, args, span)),
+                    };
                 }
-                &Token::SquL => {
-                    self.tokenizer.next();
-                    match self.tokenizer.peek() {
-                        &Token::Name(ref name) => {
+                Token::Dot => {
+                    let span = self.next_span()?;
+                    match self.tokenizer.peek()? {
+                        Token::Name(name) => {
                             let name = name.clone();
-                            self.tokenizer.next();
-                            let right = Box::new(Expr::Variable(name));
-                            left = Box::new(Expr::Index(left, right));
-                        }
-                        &Token::Number(idx) => {
-                            self.tokenizer.next();
-                            let right = Box::new(Expr::Constant(SunValue::from(idx)));
-                            left = Box::new(Expr::Index(left, right));
-                        }
-                        &Token::String(ref key) => {
-                            let key = key.clone();
-                            self.tokenizer.next();
-                            let right = Box::new(Expr::Constant(SunValue::from(key)));
-                            left = Box::new(Expr::Index(left, right));
+                            self.next()?;
+                            let right = Box::new(Expr::Constant(SunValue::from(name)));
+                            left = Box::new(Expr::Dot(left, right, span));
                         }
-                        _ => {
-                            let e = SunError::IndexError(format!(
-                                "invalid index statement at line {}",
-                                self.tokenizer.line()
+                        other => {
+                            let e = SunError::AttributeError(format!(
+                                "expected attribute name, but got `{other:?}`"
                             ));
-                            error_output(e)
+                            let span = self.tokenizer.peek_span()?;
+                            let help = "use `t[k]` to index with an expression";
+                            return Err(self.tokenizer.diagnose(e, span, Some(help)));
                         }
                     }
-                    self.expect(Token::SquR);
+                }
+                Token::SquL => {
+                    let span = self.next_span()?;
+                    let mut right = self.parse_expr()?;
+                    self.unexpect_assign(&mut right)?;
+                    left = Box::new(Expr::Index(left, right, span));
+                    self.expect(Token::SquR)?;
                 }
                 _ => break,
             }
         }
-        left
+        Ok(left)
     }
 
     /// metacall
-    fn parse_metacall(&mut self) -> Box<Expr> {
-        let name = self.parse_5();
-        match self.tokenizer.peek() {
-            &Token::Colon => {
-                self.tokenizer.next();
-                let method = self.parse_5();
+    fn parse_metacall(&mut self) -> Result<Box<Expr>, SunError> {
+        let name = self.parse_5()?;
+        match self.tokenizer.peek()? {
+            &Token::Colon if self.ternary_depth == 0 => {
+                self.next()?;
+                let method = self.parse_5()?;
                 match (*name, *method) {
-                    (Expr::Variable(n), Expr::Variable(m)) => Box::new(Expr::MetaCall(n, m)),
+                    (
+                        Expr::Variable(n) | Expr::Local(n, _) | Expr::Upvalue(n, _),
+                        Expr::Variable(m) | Expr::Local(m, _) | Expr::Upvalue(m, _),
+                    ) => Ok(Box::new(Expr::MetaCall(n, m))),
                     _ => {
-                        let e = SunError::CallError(format!(
-                            "invalid meta call statement at line {}",
-                            self.tokenizer.line()
-                        ));
-                        error_output(e)
+                        let e = SunError::CallError("invalid meta call statement".to_string());
+                        Err(e)
                     }
                 }
             }
-            _ => name,
+            _ => Ok(name),
         }
     }
 
     /// name
-    fn parse_5(&mut self) -> Box<Expr> {
-        match self.tokenizer.peek() {
-            &Token::Name(ref name) => {
+    fn parse_5(&mut self) -> Result<Box<Expr>, SunError> {
+        match self.tokenizer.peek()? {
+            Token::Name(name) => {
                 let name = name.clone();
-                self.tokenizer.next();
-                Box::new(Expr::Variable(name))
+                self.next()?;
+                Ok(self.resolve(name))
             }
             &Token::CurL => {
                 let mut args = Vec::new();
-                self.tokenizer.next();
-                if self.tokenizer.peek() != &Token::CurR {
-                    args.push(self.parse_pair());
-                    while self.tokenizer.peek() == &Token::Comma {
-                        self.tokenizer.next();
-                        args.push(self.parse_pair());
+                self.next()?;
+                if self.tokenizer.peek()? != &Token::CurR {
+                    args.push(self.parse_pair()?);
+                    while self.tokenizer.peek()? == &Token::Comma {
+                        self.next()?;
+                        args.push(self.parse_pair()?);
                     }
                 }
-                self.expect(Token::CurR);
-                Box::new(Expr::TableCreate(args))
+                self.expect(Token::CurR)?;
+                Ok(Box::new(Expr::TableCreate(args)))
             }
+            &Token::DefFunction => self.parse_lambda(),
             _ => self.parse_primary(),
         }
     }
 
-    /// key-value pair
-    fn parse_pair(&mut self) -> Box<Expr> {
-        let left = self.parse_primary();
-        match self.tokenizer.peek() {
-            &Token::Colon => {
-                self.tokenizer.next();
-                let right = self.parse_expr();
-                match *left {
-                    Expr::Constant(key) => match key {
-                        key @ SunValue::String(_) => {
-                            Box::new(Expr::PairCreate((&key).into(), right))
-                        }
-                        other => {
-                            let e = SunError::KeyError(format!(
-                                "`{other}` is not a valid key at line {}",
-                                self.tokenizer.line()
-                            ));
-                            error_output(e)
-                        }
-                    },
-                    _ => {
-                        let e = SunError::KeyError(format!(
-                            "expression is not a valid key at line {}",
-                            self.tokenizer.line()
+    /// key-value pair，键可以是任意表达式，在运行时转换为 `TableKey`
+    fn parse_pair(&mut self) -> Result<Box<Expr>, SunError> {
+        // 以变量名开头的是数组的值，之后的 `:` 为元调用
+        if let Token::Name(_) = self.tokenizer.peek()? {
+            return self.parse_expr();
+        }
+        self.ternary_depth += 1;
+        let left = self.parse_expr();
+        self.ternary_depth -= 1;
+        let left = left?;
+        if self.tokenizer.peek()? != &Token::Colon {
+            return Ok(left);
+        }
+        self.next()?;
+        if let Expr::Constant(key) = &*left {
+            if TableKey::from_value(key).is_none() {
+                let e = SunError::KeyError(format!("`{key}` is not a valid key"));
+                return Err(e);
+            }
+        }
+        let right = self.parse_expr()?;
+        Ok(Box::new(Expr::PairCreate(left, right)))
+    }
+
+    /// def function
+    fn parse_deffunc(&mut self) -> Result<Box<Expr>, SunError> {
+        self.expect(Token::DefFunction)?;
+        // 语句开头的匿名函数
+        if self.tokenizer.peek()? == &Token::ParL {
+            return self.parse_funcbody("anonymous");
+        }
+        let name = match self.next()? {
+            Some(Token::Name(name)) => name,
+            other => {
+                let e =
+                    SunError::SymbolError(format!("expected function name, but got `{:?}`", other));
+                let span = self.tokenizer.span();
+                return Err(self.tokenizer.diagnose(e, span, None));
+            }
+        };
+        // 先声明函数名，函数体中才能引用自身
+        let slot = self.declare(&name);
+        let function = self.parse_funcbody(&name)?;
+        Ok(self.assign(name, slot, function))
+    }
+
+    /// 类定义，`new` 方法作为构造函数，类名绑定到构造函数
+    fn parse_defclass(&mut self) -> Result<Box<Expr>, SunError> {
+        self.expect(Token::DefClass)?;
+        let name = self.parse_class_name("class name")?;
+        let base = if self.tokenizer.peek()? == &Token::Colon {
+            self.next()?;
+            Some(self.parse_class_name("base class name")?)
+        } else {
+            None
+        };
+        self.expect(Token::CurL)?;
+        // 先声明类名，方法中才能引用构造函数
+        let slot = self.declare(&name);
+        let mut constructor = None;
+        let mut methods: Vec<(String, Box<Expr>)> = Vec::new();
+        loop {
+            match self.next()? {
+                Some(Token::CurR) => break,
+                Some(Token::Semi) => continue,
+                Some(Token::DefFunction) => {
+                    let method = self.parse_class_name("method name")?;
+                    let full_name = format!("{name}.{method}");
+                    if constructor.is_some() && method == "new"
+                        || methods.iter().any(|(m, _)| *m == method)
+                    {
+                        let e = SunError::SymbolError(format!(
+                            "duplicate method `{method}` in class `{name}`"
                         ));
-                        error_output(e)
+                        let span = self.tokenizer.span();
+                        return Err(self.tokenizer.diagnose(e, span, None));
+                    }
+                    if method == "new" {
+                        constructor = Some(self.parse_function(&full_name, Some(&name))?);
+                    } else {
+                        methods.push((method, self.parse_funcbody(&full_name)?));
                     }
                 }
+                other => {
+                    let e = SunError::SymbolError(format!(
+                        "expected method definition, but got `{:?}`",
+                        other
+                    ));
+                    let span = self.tokenizer.span();
+                    let help = "a class body can only contain `fn` definitions";
+                    return Err(self.tokenizer.diagnose(e, span, Some(help)));
+                }
             }
-            _ => left,
         }
+        // 没有定义 `new` 时构造函数不接收参数，返回空的实例
+        let constructor = constructor.unwrap_or_else(|| {
+            Box::new(Expr::DefFunction(
+                format!("{name}.new"),
+                Vec::new(),
+                vec![Box::new(Expr::NewInstance(name.clone()))],
+                0,
+                Vec::new(),
+            ))
+        });
+        self.derive_compare(&name, &mut methods);
+        let class = Box::new(Expr::DefClass(name.clone(), base, methods, constructor));
+        Ok(self.assign(name, slot, class))
     }
 
-    /// def function
-    fn parse_deffunc(&mut self) -> Box<Expr> {
-        self.parse_0()
+    /// 类定义了 `eq` 或 `less` 时，由它们补全 `!=`、`>`、`<=` 和 `>=` 对应的方法，
+    /// 补全的方法只调用 `self` 的方法，另一个操作数可以是任意类型的值
+    fn derive_compare(&self, class: &str, methods: &mut Vec<(String, Box<Expr>)>) {
+        let defined = |method: &str| methods.iter().any(|(m, _)| m == method);
+        let span = self.tokenizer.span();
+        let this = || Box::new(Expr::Local("self".to_string(), 0));
+        let other = || Box::new(Expr::Local("other".to_string(), 1));
+        let not = |expr: Expr| Expr::Not(Box::new(expr), span.clone());
+        let mut derived = Vec::new();
+        if defined("eq") {
+            derived.push(("noteq", not(Expr::Eq(this(), other(), span.clone()))));
+        }
+        if defined("less") {
+            let le = || {
+                let less = Box::new(Expr::Less(this(), other(), span.clone()));
+                let eq = Box::new(Expr::Eq(this(), other(), span.clone()));
+                Expr::Or(less, eq, span.clone())
+            };
+            derived.push(("le", le()));
+            derived.push(("greater", not(le())));
+            derived.push(("ge", not(Expr::Less(this(), other(), span.clone()))));
+        }
+        let derived: Vec<_> = derived
+            .into_iter()
+            .filter(|(method, _)| !defined(method))
+            .collect();
+        for (method, body) in derived {
+            let function = Expr::DefFunction(
+                format!("{class}.{method}"),
+                vec!["self".to_string(), "other".to_string()],
+                vec![Box::new(body)],
+                2,
+                Vec::new(),
+            );
+            methods.push((method.to_string(), Box::new(function)));
+        }
+    }
+
+    /// 类定义中的名字 `what`: 名字的用途，用于错误信息
+    fn parse_class_name(&mut self, what: &str) -> Result<String, SunError> {
+        match self.next()? {
+            Some(Token::Name(name)) => Ok(name),
+            other => {
+                let e = SunError::SymbolError(format!("expected {what}, but got `{:?}`", other));
+                let span = self.tokenizer.span();
+                Err(self.tokenizer.diagnose(e, span, None))
+            }
+        }
+    }
+
+    /// 匿名函数
+    fn parse_lambda(&mut self) -> Result<Box<Expr>, SunError> {
+        self.expect(Token::DefFunction)?;
+        self.parse_funcbody("anonymous")
+    }
+
+    /// 函数的形参列表和函数体
+    fn parse_funcbody(&mut self, name: &str) -> Result<Box<Expr>, SunError> {
+        self.parse_function(name, None)
+    }
+
+    /// 函数的形参列表和函数体 `class`: 构造函数所属的类名，
+    /// 构造函数开始时新建实例保存在局部变量 `self` 中，结束时返回该实例
+    fn parse_function(&mut self, name: &str, class: Option<&str>) -> Result<Box<Expr>, SunError> {
+        self.expect(Token::ParL)?;
+        let params = self.parse_params()?;
+        self.expect(Token::CurL)?;
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let mut scope = FuncScope::new();
+        for param in &params {
+            scope.declare(param);
+        }
+        let this = class.map(|_| scope.declare("self"));
+        self.scopes.push(scope);
+        let mut bodys = self.parse_chunk()?;
+        let scope = self.scopes.pop().unwrap();
+        if let (Some(class), Some(slot)) = (class, this) {
+            let instance = Box::new(Expr::NewInstance(class.to_string()));
+            let init = Expr::LocalAssign("self".to_string(), slot, instance);
+            bodys.insert(0, Box::new(init));
+            bodys.push(Box::new(Expr::Local("self".to_string(), slot)));
+        }
+        self.loop_depth = loop_depth;
+        Ok(Box::new(Expr::DefFunction(
+            name.to_string(),
+            params,
+            bodys,
+            scope.frame_size(),
+            scope.captures(),
+        )))
+    }
+
+    /// 函数的形参列表
+    fn parse_params(&mut self) -> Result<Vec<String>, SunError> {
+        let mut params: Vec<String> = Vec::new();
+        if self.tokenizer.peek()? == &Token::ParR {
+            self.next()?;
+            return Ok(params);
+        }
+        loop {
+            match self.next()? {
+                Some(Token::Name(name)) => {
+                    if params.contains(&name) {
+                        let e = SunError::SymbolError(format!("duplicate parameter `{name}`"));
+                        let span = self.tokenizer.span();
+                        return Err(self.tokenizer.diagnose(e, span, None));
+                    }
+                    params.push(name)
+                }
+                other => {
+                    let e = SunError::SymbolError(format!(
+                        "expected parameter name, but got `{:?}`",
+                        other
+                    ));
+                    let span = self.tokenizer.span();
+                    return Err(self.tokenizer.diagnose(e, span, None));
+                }
+            }
+            match self.next()? {
+                Some(Token::Comma) => continue,
+                Some(Token::ParR) => break,
+                other => {
+                    let e = SunError::SymbolError(format!(
+                        "expected `,` or `)`, but got `{:?}`",
+                        other
+                    ));
+                    return Err(e);
+                }
+            }
+        }
+        Ok(params)
     }
 
     /// if
-    fn parse_if(&mut self) -> Box<Expr> {
-        self.expect(Token::If);
-        let mut cond = self.parse_logic_unassign();
-        self.unexpect_assign(&mut cond);
-        self.expect(Token::CurL);
-        let thens = self.parse_chunk();
-        let elses = if let &Token::Else = self.tokenizer.peek() {
-            self.tokenizer.next();
-            let elses = self.parse_chunk();
+    fn parse_if(&mut self) -> Result<Box<Expr>, SunError> {
+        self.expect(Token::If)?;
+        let mut cond = self.parse_logic_unassign()?;
+        self.unexpect_assign(&mut cond)?;
+        self.expect(Token::CurL)?;
+        let thens = self.parse_chunk()?;
+        let elses = if let &Token::Else = self.tokenizer.peek()? {
+            self.next()?;
+            self.expect(Token::CurL)?;
+            let elses = self.parse_chunk()?;
             Some(elses)
         } else {
             None
         };
-        Box::new(Expr::If(cond, thens, elses))
+        Ok(Box::new(Expr::If(cond, thens, elses)))
+    }
+
+    /// if 表达式，没有 else 分支时条件不成立的值为 `Nil`
+    fn parse_if_expr(&mut self) -> Result<Box<Expr>, SunError> {
+        match *self.parse_if()? {
+            Expr::If(cond, thens, elses) => Ok(Box::new(Expr::IfExpr(
+                cond,
+                thens,
+                elses.unwrap_or_default(),
+            ))),
+            _ => unreachable!("parse if expression"),
+        }
+    }
+
+    /// match，依次尝试各分支的模式，值为匹配的分支最后的值
+    fn parse_match(&mut self) -> Result<Box<Expr>, SunError> {
+        self.expect(Token::Match)?;
+        let mut value = self.parse_logic_unassign()?;
+        self.unexpect_assign(&mut value)?;
+        self.expect(Token::CurL)?;
+        self.scope().enter_block();
+        // 被匹配的值保存在无法被引用的局部变量中
+        let slot = self.scope().declare("match");
+        let mut arms = Vec::new();
+        while self.tokenizer.peek()? != &Token::CurR {
+            self.scope().enter_block();
+            let mut binds = Vec::new();
+            let mut patterns = vec![self.parse_pattern(&mut binds)?];
+            while self.tokenizer.peek()? == &Token::Pipe {
+                self.next()?;
+                patterns.push(self.parse_pattern(&mut binds)?);
+            }
+            self.expect(Token::FatArrow)?;
+            let is_block = self.tokenizer.peek()? == &Token::CurL;
+            let bodys = if is_block {
+                self.next()?;
+                self.parse_chunk()?
+            } else {
+                vec![self.parse_expr()?]
+            };
+            self.scope().leave_block();
+            arms.push((patterns, bodys));
+            match self.tokenizer.peek()? {
+                &Token::Comma => {
+                    self.next()?;
+                }
+                &Token::CurR => {}
+                _ if is_block => {}
+                other => {
+                    let e =
+                        SunError::SymbolError(format!("unexpected token `{:?}`", other.clone()));
+                    let span = self.tokenizer.peek_span()?;
+                    let help = "arms of `match` are separated by `,`";
+                    return Err(self.tokenizer.diagnose(e, span, Some(help)));
+                }
+            }
+        }
+        self.next()?;
+        self.scope().leave_block();
+        Ok(Box::new(Expr::Match(value, slot, arms)))
+    }
+
+    /// match 的模式，`binds`: 当前分支中绑定的变量名和槽位，`|` 两侧的同名变量使用同一个槽位
+    fn parse_pattern(&mut self, binds: &mut Vec<(String, usize)>) -> Result<Pattern, SunError> {
+        match self.tokenizer.peek()? {
+            Token::Name(name) if name == "_" => {
+                self.next()?;
+                Ok(Pattern::Wildcard)
+            }
+            &Token::CurL => {
+                self.next()?;
+                let mut entries = Vec::new();
+                while self.tokenizer.peek()? != &Token::CurR {
+                    let key = match self.next()? {
+                        Some(Token::String(key)) => String::from_utf8_lossy(&key).to_string(),
+                        other => {
+                            let e = SunError::KeyError(format!(
+                                "expected string key in table pattern, but got `{:?}`",
+                                other
+                            ));
+                            let span = self.tokenizer.span();
+                            return Err(self.tokenizer.diagnose(e, span, None));
+                        }
+                    };
+                    self.expect(Token::Colon)?;
+                    let entry = match self.tokenizer.peek()? {
+                        Token::Name(name) if name != "_" => {
+                            let name = name.clone();
+                            self.next()?;
+                            let slot = match binds.iter().find(|(n, _)| *n == name) {
+                                Some(&(_, slot)) => slot,
+                                None => {
+                                    let slot = self.scope().declare(&name);
+                                    binds.push((name, slot));
+                                    slot
+                                }
+                            };
+                            (key, slot, Pattern::Wildcard)
+                        }
+                        _ => {
+                            let pattern = self.parse_pattern(binds)?;
+                            (key, self.scope().declare("match"), pattern)
+                        }
+                    };
+                    entries.push(entry);
+                    if self.tokenizer.peek()? != &Token::Comma {
+                        break;
+                    }
+                    self.next()?;
+                }
+                self.expect(Token::CurR)?;
+                Ok(Pattern::Table(entries))
+            }
+            _ => {
+                let start = self.parse_literal()?;
+                if self.tokenizer.peek()? != &Token::DotDot {
+                    return Ok(Pattern::Value(start));
+                }
+                self.next()?;
+                let end = self.parse_literal()?;
+                let number =
+                    |value: &SunValue| matches!(value, SunValue::Integer(_) | SunValue::Float(_));
+                if !number(&start) || !number(&end) {
+                    let e = SunError::SymbolError(format!(
+                        "range pattern bounds must be numbers but got `{start}` and `{end}`"
+                    ));
+                    return Err(e);
+                }
+                Ok(Pattern::Range(start, end))
+            }
+        }
+    }
+
+    /// 模式中的字面量
+    fn parse_literal(&mut self) -> Result<SunValue, SunError> {
+        let value = match self.next()? {
+            Some(Token::Integer(value)) => SunValue::from(value),
+            Some(Token::Float(value)) => SunValue::from(value),
+            Some(Token::String(value)) => SunValue::from(value),
+            Some(Token::True) => SunValue::from(true),
+            Some(Token::False) => SunValue::from(false),
+            Some(Token::Nil) => SunValue::Nil,
+            Some(Token::Sub) => match self.next()? {
+                Some(Token::Integer(value)) => SunValue::from(-value),
+                Some(Token::Float(value)) => SunValue::from(-value),
+                other => return Err(self.invalid_pattern(other)),
+            },
+            other => return Err(self.invalid_pattern(other)),
+        };
+        Ok(value)
+    }
+
+    /// 无效模式的错误
+    fn invalid_pattern(&mut self, token: Option<Token>) -> SunError {
+        let e = SunError::SymbolError(format!("invalid pattern `{:?}`", token));
+        let span = self.tokenizer.span();
+        let help = "patterns are literals, ranges `a..b`, tables `{\"k\": p}` or `_`";
+        self.tokenizer.diagnose(e, span, Some(help))
     }
 
     /// loop
-    fn parse_loop(&mut self) -> Box<Expr> {
-        self.expect(Token::Loop);
-        let mut cond = self.parse_logic_unassign();
-        self.unexpect_assign(&mut cond);
-        self.expect(Token::CurL);
-        let bodys = self.parse_chunk();
-        Box::new(Expr::Loop(cond, bodys))
+    fn parse_loop(&mut self) -> Result<Box<Expr>, SunError> {
+        self.expect(Token::Loop)?;
+        let mut cond = self.parse_logic_unassign()?;
+        self.unexpect_assign(&mut cond)?;
+        self.expect(Token::CurL)?;
+        self.loop_depth += 1;
+        let bodys = self.parse_chunk()?;
+        self.loop_depth -= 1;
+        Ok(Box::new(Expr::Loop(cond, bodys)))
+    }
+
+    /// for in，循环变量为循环体中的局部变量
+    fn parse_for(&mut self) -> Result<Box<Expr>, SunError> {
+        self.expect(Token::For)?;
+        let mut names = Vec::new();
+        loop {
+            match self.next()? {
+                Some(Token::Name(name)) => names.push(name),
+                other => {
+                    let e = SunError::SymbolError(format!(
+                        "expected loop variable name, but got `{:?}`",
+                        other
+                    ));
+                    let span = self.tokenizer.span();
+                    return Err(self.tokenizer.diagnose(e, span, None));
+                }
+            }
+            if self.tokenizer.peek()? != &Token::Comma {
+                break;
+            }
+            self.next()?;
+        }
+        self.expect(Token::In)?;
+        let span = self.tokenizer.span();
+        let iter = self.parse_logic_unassign()?;
+        let end = if self.tokenizer.peek()? == &Token::DotDot {
+            self.next()?;
+            Some(self.parse_logic_unassign()?)
+        } else {
+            None
+        };
+        self.expect(Token::CurL)?;
+        self.scope().enter_block();
+        let slots = names
+            .iter()
+            .map(|name| self.scope().declare(name))
+            .collect();
+        self.loop_depth += 1;
+        let bodys = self.parse_chunk()?;
+        self.loop_depth -= 1;
+        self.scope().leave_block();
+        Ok(Box::new(Expr::For(slots, iter, end, bodys, span)))
     }
 
     /// 禁止包含赋值语句的 and or xor
-    fn parse_logic_unassign(&mut self) -> Box<Expr> {
-        let mut left = self.parse_compare_unassign();
-        self.unexpect_assign(&mut left);
+    fn parse_logic_unassign(&mut self) -> Result<Box<Expr>, SunError> {
+        let mut left = self.parse_compare_unassign()?;
+        self.unexpect_assign(&mut left)?;
         loop {
-            match self.tokenizer.peek() {
-                &Token::And => {
-                    self.tokenizer.next();
-                    let mut right = self.parse_compare_unassign();
-                    self.unexpect_assign(&mut right);
-                    left = Box::new(Expr::And(left, right));
-                }
-                &Token::Or => {
-                    self.tokenizer.next();
-                    let mut right = self.parse_compare_unassign();
-                    self.unexpect_assign(&mut right);
-                    left = Box::new(Expr::Or(left, right));
-                }
-                &Token::Xor => {
-                    self.tokenizer.next();
-                    let mut right = self.parse_compare_unassign();
-                    self.unexpect_assign(&mut right);
-                    left = Box::new(Expr::Xor(left, right));
+            match *self.tokenizer.peek()? {
+                Token::And => {
+                    let span = self.next_span()?;
+                    let mut right = self.parse_compare_unassign()?;
+                    self.unexpect_assign(&mut right)?;
+                    left = Box::new(Expr::And(left, right, span));
+                }
+                Token::Or => {
+                    let span = self.next_span()?;
+                    let mut right = self.parse_compare_unassign()?;
+                    self.unexpect_assign(&mut right)?;
+                    left = Box::new(Expr::Or(left, right, span));
+                }
+                Token::Xor => {
+                    let span = self.next_span()?;
+                    let mut right = self.parse_compare_unassign()?;
+                    self.unexpect_assign(&mut right)?;
+                    left = Box::new(Expr::Xor(left, right, span));
                 }
                 _ => break,
             }
         }
-        left
+        Ok(left)
     }
 
     /// 禁止包含赋值语句的 compare
-    fn parse_compare_unassign(&mut self) -> Box<Expr> {
-        let mut left = self.parse_0();
-        self.unexpect_assign(&mut left);
-        match self.tokenizer.peek() {
-            &Token::Eq => {
-                self.tokenizer.next();
-                let mut right = self.parse_0();
-                self.unexpect_assign(&mut right);
-                Box::new(Expr::Eq(left, right))
-            }
-            &Token::NotEq => {
-                self.tokenizer.next();
-                let mut right = self.parse_0();
-                self.unexpect_assign(&mut right);
-                Box::new(Expr::NotEq(left, right))
-            }
-            &Token::Le => {
-                self.tokenizer.next();
-                let mut right = self.parse_0();
-                self.unexpect_assign(&mut right);
-                Box::new(Expr::Le(left, right))
-            }
-            &Token::Ge => {
-                self.tokenizer.next();
-                let mut right = self.parse_0();
-                self.unexpect_assign(&mut right);
-                Box::new(Expr::Ge(left, right))
-            }
-            &Token::Less => {
-                self.tokenizer.next();
-                let mut right = self.parse_0();
-                self.unexpect_assign(&mut right);
-                Box::new(Expr::Less(left, right))
-            }
-            &Token::Greater => {
-                self.tokenizer.next();
-                let mut right = self.parse_0();
-                self.unexpect_assign(&mut right);
-                Box::new(Expr::Greater(left, right))
-            }
-            _ => left,
+    fn parse_compare_unassign(&mut self) -> Result<Box<Expr>, SunError> {
+        let mut left = self.parse_0()?;
+        self.unexpect_assign(&mut left)?;
+        match *self.tokenizer.peek()? {
+            Token::Eq => {
+                let span = self.next_span()?;
+                let mut right = self.parse_0()?;
+                self.unexpect_assign(&mut right)?;
+                Ok(Box::new(Expr::Eq(left, right, span)))
+            }
+            Token::NotEq => {
+                let span = self.next_span()?;
+                let mut right = self.parse_0()?;
+                self.unexpect_assign(&mut right)?;
+                Ok(Box::new(Expr::NotEq(left, right, span)))
+            }
+            Token::Le => {
+                let span = self.next_span()?;
+                let mut right = self.parse_0()?;
+                self.unexpect_assign(&mut right)?;
+                Ok(Box::new(Expr::Le(left, right, span)))
+            }
+            Token::Ge => {
+                let span = self.next_span()?;
+                let mut right = self.parse_0()?;
+                self.unexpect_assign(&mut right)?;
+                Ok(Box::new(Expr::Ge(left, right, span)))
+            }
+            Token::Less => {
+                let span = self.next_span()?;
+                let mut right = self.parse_0()?;
+                self.unexpect_assign(&mut right)?;
+                Ok(Box::new(Expr::Less(left, right, span)))
+            }
+            Token::Greater => {
+                let span = self.next_span()?;
+                let mut right = self.parse_0()?;
+                self.unexpect_assign(&mut right)?;
+                Ok(Box::new(Expr::Greater(left, right, span)))
+            }
+            _ => Ok(left),
         }
     }
 
     /// 原子语句
-    fn parse_primary(&mut self) -> Box<Expr> {
-        match self.tokenizer.peek() {
-            &Token::Number(ref value) => {
-                let value = value.clone();
-                self.tokenizer.next();
-                Box::new(Expr::Constant(SunValue::from(value)))
+    fn parse_primary(&mut self) -> Result<Box<Expr>, SunError> {
+        match self.tokenizer.peek()? {
+            &Token::Integer(value) => {
+                self.next()?;
+                Ok(Box::new(Expr::Constant(SunValue::from(value))))
             }
-            &Token::String(ref value) => {
+            &Token::Float(value) => {
+                self.next()?;
+                Ok(Box::new(Expr::Constant(SunValue::from(value))))
+            }
+            Token::String(value) => {
                 let value = value.clone();
-                self.tokenizer.next();
-                Box::new(Expr::Constant(SunValue::from(value)))
+                self.next()?;
+                Ok(Box::new(Expr::Constant(SunValue::from(value))))
             }
+            &Token::FormatStart => self.parse_format(),
+            &Token::Import => Ok(Box::new(Expr::Import(self.parse_module()?))),
+            &Token::If => self.parse_if_expr(),
+            &Token::Match => self.parse_match(),
             &Token::True => {
-                self.tokenizer.next();
-                Box::new(Expr::Constant(SunValue::from(true)))
+                self.next()?;
+                Ok(Box::new(Expr::Constant(SunValue::from(true))))
             }
             &Token::False => {
-                self.tokenizer.next();
-                Box::new(Expr::Constant(SunValue::from(false)))
+                self.next()?;
+                Ok(Box::new(Expr::Constant(SunValue::from(false))))
             }
             &Token::Nil => {
-                self.tokenizer.next();
-                Box::new(Expr::Constant(SunValue::Nil))
+                self.next()?;
+                Ok(Box::new(Expr::Constant(SunValue::Nil)))
             }
             &Token::ParL => {
-                self.tokenizer.next();
-                let expr = self.parse_expr();
-                self.expect(Token::ParR);
-                expr
+                self.next()?;
+                let expr = self.parse_expr()?;
+                self.expect(Token::ParR)?;
+                Ok(expr)
             }
             &Token::Eos => {
-                let e = SunError::SymbolError(format!(
-                    "incomplete statement at line {}",
-                    self.tokenizer.line()
-                ));
-                error_output(e)
+                let e = SunError::SymbolError("incomplete statement".to_string());
+                let span = self.tokenizer.peek_span()?;
+                Err(self.tokenizer.diagnose(e, span, None))
             }
             other => {
-                let e = SunError::SymbolError(format!(
-                    "unexpected token `{:?}` at line {}",
-                    other.clone(),
-                    self.tokenizer.line()
-                ));
-                error_output(e)
+                let e = SunError::SymbolError(format!("unexpected token `{:?}`", other.clone()));
+                let span = self.tokenizer.peek_span()?;
+                Err(self.tokenizer.diagnose(e, span, None))
             }
         }
     }
 
+    /// f-string，从字符串开始依次用 `add` 拼接各段字符串和嵌入表达式的值
+    fn parse_format(&mut self) -> Result<Box<Expr>, SunError> {
+        let span = self.next_span()?;
+        let mut expr: Option<Box<Expr>> = None;
+        loop {
+            let part = match self.tokenizer.peek()? {
+                Token::FormatPart(value) => {
+                    let value = value.clone();
+                    self.next()?;
+                    Box::new(Expr::Constant(SunValue::from(value)))
+                }
+                Token::CurL => {
+                    self.next()?;
+                    let mut part = self.parse_expr()?;
+                    self.unexpect_assign(&mut part)?;
+                    self.expect(Token::CurR)?;
+                    part
+                }
+                Token::FormatEnd => {
+                    self.next()?;
+                    break;
+                }
+                _ => unreachable!("parse format"),
+            };
+            expr = Some(match expr {
+                Some(left) => Box::new(Expr::Add(left, part, span.clone())),
+                // 以嵌入表达式开始时先拼接到空字符串上，保证调用的是 String 的 `add`
+                None if matches!(*part, Expr::Constant(SunValue::String(_))) => part,
+                None => {
+                    let empty = Box::new(Expr::Constant(SunValue::from("")));
+                    Box::new(Expr::Add(empty, part, span.clone()))
+                }
+            });
+        }
+        Ok(expr.unwrap_or_else(|| Box::new(Expr::Constant(SunValue::from("")))))
+    }
+
     /// 检查下一个 `Token` 是否为期望的 `Token`，否则打印错误
-    fn expect(&mut self, token: Token) -> Token {
-        match self.tokenizer.peek() {
-            t if t == &token => self.tokenizer.next().unwrap(),
+    fn expect(&mut self, token: Token) -> Result<Token, SunError> {
+        match self.tokenizer.peek()? {
+            t if t == &token => self.next()?.unwrap(),
             other => {
                 let e =
                     SunError::SymbolError(format!("expected `{token:?}`, but got `{:?}`", other));
-                error_output(e);
+                let span = self.tokenizer.peek_span()?;
+                return Err(self.tokenizer.diagnose(e, span, None));
             }
         };
-        token
+        Ok(token)
     }
 
     /// 检查当前语句是否不为赋值语句
-    fn unexpect_assign(&mut self, expr: &mut Box<Expr>) {
-        if let Expr::Assign(_, _) | Expr::TableAssign(_, _) = *(*expr) {
-            let e = SunError::SymbolError(format!(
-                "assign statement can't be condition at line {}",
-                self.tokenizer.line()
-            ));
-            error_output(e)
+    fn unexpect_assign(&mut self, expr: &mut Box<Expr>) -> Result<(), SunError> {
+        if let Expr::Assign(_, _)
+        | Expr::LocalAssign(_, _, _)
+        | Expr::UpvalueAssign(_, _, _)
+        | Expr::Let(_, _, _)
+        | Expr::TableAssign(..) = *(*expr)
+        {
+            let e = SunError::SymbolError("assign statement can't be condition".to_string());
+            let span = self.tokenizer.span();
+            return Err(self
+                .tokenizer
+                .diagnose(e, span, Some("use `==` to compare values")));
+        }
+        Ok(())
+    }
+
+    /// 向前获取一个 `Token`
+    fn next(&mut self) -> Result<Option<Token>, SunError> {
+        self.tokenizer.next().transpose()
+    }
+
+    /// 获取下一个 `Token` 并返回它的位置
+    fn next_span(&mut self) -> Result<Span, SunError> {
+        self.next()?;
+        Ok(self.tokenizer.span())
+    }
+
+    /// 当前函数的作用域
+    fn scope(&mut self) -> &mut FuncScope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    /// 按变量名解析为局部变量、捕获变量或全局变量
+    fn resolve(&mut self, name: String) -> Box<Expr> {
+        if let Some(slot) = self.scope().resolve(&name) {
+            return Box::new(Expr::Local(name, slot));
+        }
+        match self.resolve_upvalue(self.scopes.len() - 1, &name) {
+            Some(index) => Box::new(Expr::Upvalue(name, index)),
+            None => Box::new(Expr::Variable(name)),
+        }
+    }
+
+    /// 在外层函数中查找变量，找到后逐层记录为捕获变量，返回在第 depth 层函数中的序号
+    fn resolve_upvalue(&mut self, depth: usize, name: &str) -> Option<usize> {
+        if depth == 0 {
+            return None;
+        }
+        if let Some(index) = self.scopes[depth].find_upvalue(name) {
+            return Some(index);
+        }
+        let capture = match self.scopes[depth - 1].resolve(name) {
+            Some(slot) => Capture::Local(slot),
+            None => Capture::Upvalue(self.resolve_upvalue(depth - 1, name)?),
+        };
+        Some(self.scopes[depth].add_upvalue(name, capture))
+    }
+
+    /// 在当前语句块中声明变量，顶层语段的语句块之外声明的变量为全局变量
+    fn declare(&mut self, name: &str) -> Option<usize> {
+        if self.scopes.len() == 1 && !self.scope().in_block() {
+            None
+        } else {
+            Some(self.scope().declare(name))
+        }
+    }
+
+    /// 生成对新声明变量的赋值语句，局部变量每次声明都使用新的存储单元
+    fn assign(&self, name: String, slot: Option<usize>, value: Box<Expr>) -> Box<Expr> {
+        match slot {
+            Some(slot) => Box::new(Expr::Let(name, slot, value)),
+            None => Box::new(Expr::Assign(name, value)),
         }
     }
 }
@@ -0,0 +1,95 @@
+use sun_core::container::Capture;
+
+/// 函数的作用域，记录局部变量在调用帧中的槽位
+#[derive(Debug)]
+pub struct FuncScope {
+    /// 块作用域栈，每层保存该语句块中声明的变量名和槽位
+    blocks: Vec<Vec<(String, usize)>>,
+    /// 下一个可用的槽位
+    next_slot: usize,
+    /// 同时存活的局部变量的最大数量，即调用帧的大小
+    max_slots: usize,
+    /// 从外层函数捕获的变量名和来源
+    upvalues: Vec<(String, Capture)>,
+}
+
+impl FuncScope {
+    /// 创建新的函数作用域
+    pub fn new() -> Self {
+        FuncScope {
+            blocks: Vec::new(),
+            next_slot: 0,
+            max_slots: 0,
+            upvalues: Vec::new(),
+        }
+    }
+
+    /// 进入新的语句块
+    pub fn enter_block(&mut self) {
+        self.blocks.push(Vec::new());
+    }
+
+    /// 离开语句块，块中声明的变量槽位可以被之后的语句块复用
+    pub fn leave_block(&mut self) {
+        if let Some(block) = self.blocks.pop() {
+            self.next_slot -= block.len();
+        }
+    }
+
+    /// 是否处于语句块中
+    pub fn in_block(&self) -> bool {
+        !self.blocks.is_empty()
+    }
+
+    /// 在当前语句块中声明局部变量，返回分配的槽位
+    pub fn declare(&mut self, name: &str) -> usize {
+        if self.blocks.is_empty() {
+            self.enter_block();
+        }
+        let slot = self.next_slot;
+        self.next_slot += 1;
+        self.max_slots = self.max_slots.max(self.next_slot);
+        self.blocks
+            .last_mut()
+            .unwrap()
+            .push((name.to_string(), slot));
+        slot
+    }
+
+    /// 由内向外查找局部变量的槽位
+    pub fn resolve(&self, name: &str) -> Option<usize> {
+        self.blocks
+            .iter()
+            .rev()
+            .flat_map(|block| block.iter().rev())
+            .find(|(n, _)| n == name)
+            .map(|(_, slot)| *slot)
+    }
+
+    /// 查找已捕获变量的序号
+    pub fn find_upvalue(&self, name: &str) -> Option<usize> {
+        self.upvalues.iter().position(|(n, _)| n == name)
+    }
+
+    /// 添加捕获的变量，返回捕获变量的序号
+    pub fn add_upvalue(&mut self, name: &str, capture: Capture) -> usize {
+        self.upvalues.push((name.to_string(), capture));
+        self.upvalues.len() - 1
+    }
+
+    /// 获取调用帧的大小
+    pub fn frame_size(&self) -> usize {
+        self.max_slots
+    }
+
+    /// 获取捕获变量的来源
+    pub fn captures(&self) -> Vec<Capture> {
+        self.upvalues.iter().map(|(_, capture)| *capture).collect()
+    }
+}
+
+impl Default for FuncScope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod prelude;
 
 pub use prelude::prelude;
@@ -1,17 +1,17 @@
 use crate::{
     add_prelude_methods, add_prelude_sys_methods,
     value::{
-        sun_boolean::SunBoolean, sun_function::SunFunction, sun_nil::SunNil, sun_number::SunNumber,
-        sun_table::SunTable,
+        sun_boolean::SunBoolean, sun_function::SunFunction, sun_instance::SunInstance,
+        sun_nil::SunNil, sun_number::SunNumber, sun_string::SunString, sun_table::SunTable,
     },
 };
 use std::{collections::HashMap, process};
 use sun_core::{
     add_metas,
     container::{Function, RustFunction, SunValue, SysFunction},
-    meta::SunMeta,
+    meta::{SunBase, SunMeta},
     utils::{
-        log::{error_output, log_output, warn_output},
+        log::{log_output, warn_output},
         object::_type,
         IsMachine, SunError, SunObject, SunPointer,
     },
@@ -20,7 +20,7 @@ use sun_core::{
 /// 预导入
 pub fn prelude(
     value_map: &mut HashMap<String, SunPointer>,
-    meta_map: &mut HashMap<&'static str, SunMeta>,
+    meta_map: &mut HashMap<String, SunMeta>,
 ) {
     add_metas!(
         meta_map,
@@ -28,10 +28,17 @@ pub fn prelude(
         ("Nil", SunNil),
         ("Bool", SunBoolean),
         ("Number", SunNumber),
+        ("String", SunString),
         ("Table", SunTable),
-        ("Function", SunFunction)
+        ("Function", SunFunction),
+        ("Class", SunInstance)
     );
-    add_prelude_methods!(value_map, print, exit);
+    // `Integer` 和 `Float` 继承 `Number` 的元方法
+    for name in ["Integer", "Float"] {
+        let base = SunBase::Other("Number".to_string());
+        meta_map.insert(name.to_string(), SunMeta::new(name, base));
+    }
+    add_prelude_methods!(value_map, print, exit, error);
     add_prelude_sys_methods!(value_map, drop, show);
     value_map.insert("type".to_string(), SunPointer::new(SunValue::from(_type())));
 }
@@ -59,53 +66,66 @@ macro_rules! add_prelude_sys_methods {
 }
 
 /// 打印变量
-fn print(args: Vec<SunPointer>) -> Vec<SunPointer> {
+fn print(args: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
     let log = args
         .iter()
         .map(|arg| arg.get().to_string())
         .collect::<Vec<_>>()
         .join(", ");
     log_output(log);
-    Vec::new()
+    Ok(Vec::new())
 }
 
 /// 退出程序
-fn exit(_: Vec<SunPointer>) -> Vec<SunPointer> {
+fn exit(_: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
     process::exit(0)
 }
 
+/// 抛出错误
+fn error(args: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
+    let message = args
+        .iter()
+        .map(|arg| arg.get().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(SunError::UserError(message))
+}
+
 /// 删除全局变量
-fn drop(vm: &mut dyn IsMachine) {
+fn drop(vm: &mut dyn IsMachine) -> Result<(), SunError> {
     match vm.pop() {
         Some(p) => match p.get() {
             name @ SunValue::String(_) => {
-                vm.drop((&name).to_string().as_str());
-            }
-            other => {
-                let e = SunError::ParaError(format!("need variable name but got `{}`", other));
-                error_output(e);
+                vm.drop(name.to_string().as_str());
+                Ok(())
             }
+            other => Err(SunError::ParaError(format!(
+                "need variable name but got `{}`",
+                other
+            ))),
         },
-        None => {
-            let e = SunError::RunError(format!("stack is empty so failed to find attribute"));
-            error_output(e);
-        }
+        None => Err(SunError::RunError(
+            "stack is empty so failed to find attribute".to_string(),
+        )),
     }
 }
 
 /// 显示全局变量
-fn show(vm: &mut dyn IsMachine) {
+fn show(vm: &mut dyn IsMachine) -> Result<(), SunError> {
     match vm.pop() {
         Some(p) => match p.get() {
-            para @ SunValue::String(_) => match (&para).to_string().as_str() {
+            para @ SunValue::String(_) => match para.to_string().as_str() {
                 "global" => vm.show_global(),
                 other => warn_output(format!("invalid 'show' parameter `{other}`")),
             },
             other => {
-                let e = SunError::ParaError(format!("need 'show' parameter but got `{}`", other));
-                error_output(e);
+                return Err(SunError::ParaError(format!(
+                    "need 'show' parameter but got `{}`",
+                    other
+                )))
             }
         },
-        None => warn_output(format!("failed to got 'show' parameter")),
+        None => warn_output("failed to got 'show' parameter".to_string()),
     }
+    Ok(())
 }
@@ -1,2 +1,3 @@
 pub mod token;
+#[allow(clippy::module_inception)]
 pub mod tokenizer;
@@ -8,6 +8,7 @@ pub enum Token {
     Return,
     Break,
     Continue,
+    Let,
 
     // if else
     If,
@@ -15,22 +16,38 @@ pub enum Token {
 
     // loop
     Loop,
+    For,
+    In,
 
-    // @ fn
+    // try catch
+    Try,
+    Catch,
+
+    // match => |
+    Match,
+    FatArrow,
+    Pipe,
+
+    // @ fn class
     Import,
     DefFunction,
+    DefClass,
 
-    // && || ! ^
+    // && || ! ^^
     And,
     Or,
     Not,
     Xor,
 
-    // + - * / %
+    // ^
+    Pow,
+
+    // + - * / ~/ %
     Add,
     Sub,
     Mul,
     Div,
+    FloorDiv,
     Mod,
 
     // == != <= >= < > =
@@ -42,6 +59,15 @@ pub enum Token {
     Greater,
     Assign,
 
+    // += -= *= /= ~/= %= ^=
+    AddAssign,
+    SubAssign,
+    MulAssign,
+    DivAssign,
+    FloorDivAssign,
+    ModAssign,
+    PowAssign,
+
     // () {} []
     ParL,
     ParR,
@@ -50,15 +76,19 @@ pub enum Token {
     SquL,
     SquR,
 
-    // . , : ; ::
+    // . .. ... , : ; :: ?
     Dot,
+    DotDot,
+    Ellipsis,
     Comma,
     Colon,
     Semi,
     DoubleColon,
+    Question,
 
     // constants value
-    Number(f64),
+    Integer(i64),
+    Float(f64),
 
     // name
     Name(String),
@@ -66,6 +96,11 @@ pub enum Token {
     // string
     String(Vec<u8>),
 
+    // f"...{expr}..."
+    FormatStart,
+    FormatPart(Vec<u8>),
+    FormatEnd,
+
     // end
     Eos,
 }
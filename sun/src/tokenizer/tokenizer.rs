@@ -3,11 +3,9 @@ use std::{
     io::{Bytes, Read},
     iter::Peekable,
     mem::replace,
+    rc::Rc,
 };
-use sun_core::utils::{
-    err::SunError,
-    log::{debug_output, error_output},
-};
+use sun_core::utils::{err::SunError, log::debug_output, Diagnostic, Span};
 
 /// 词法分析器的结构体
 #[derive(Debug)]
@@ -18,6 +16,24 @@ pub struct Tokenizer<R: Read> {
     ahead: Token,
     /// `line_num`: 当前分析的行号
     line_num: u64,
+    /// `col_num`: 当前分析的列号
+    col_num: u64,
+    /// `name`: 源文件名
+    name: Rc<str>,
+    /// `start`: 正在读取的 `Token` 的开始位置
+    start: (u64, u64),
+    /// `ahead_span`: 向前seek的 `Token` 的位置
+    ahead_span: Span,
+    /// `span`: 最近获取的 `Token` 的位置
+    span: Span,
+    /// `lines`: 已读取的源码，按行保存，用于输出诊断信息
+    lines: Vec<Vec<u8>>,
+    /// `pending`: 读取数字时一并读取的 `..` 及其开始位置
+    pending: Option<(Token, (u64, u64))>,
+    /// `formats`: 正在读取的 f-string 的开始位置和嵌入表达式中未闭合的 `{` 数量，由外向内
+    formats: Vec<((u64, u64), usize)>,
+    /// `format_part`: 是否正在读取 f-string 的字符串部分
+    format_part: bool,
     /// `check`: 词法分析检查的标志
     check: bool,
 }
@@ -26,22 +42,47 @@ impl<R: Read> Tokenizer<R> {
     /**
         `Tokenizer` 的构造函数
         + `input`: File Sun文件 / 标准输入
+        + `name`: 源文件名，用于标记 `Token` 的位置
         + `ahead`: 初始值为 Token::EOS
     */
-    pub fn new(input: R, check: bool) -> Self {
+    #[allow(clippy::unbuffered_bytes)]
+    pub fn new(input: R, name: &str, check: bool) -> Self {
+        let name: Rc<str> = Rc::from(name);
         Tokenizer {
             input: input.bytes().peekable(),
             ahead: Token::Eos,
             line_num: 1,
+            col_num: 0,
+            start: (1, 0),
+            ahead_span: Span::new(name.clone(), 1, 0),
+            span: Span::new(name.clone(), 1, 0),
+            lines: vec![Vec::new()],
+            pending: None,
+            formats: Vec::new(),
+            format_part: false,
+            name,
             check,
         }
     }
 
     /**
-        向前读取一个u8字符，如果读取为空则返回终止符 None
+        向前读取一个u8字符，如果读取为空则返回终止符 None，同时更新行号和列号
     */
     fn read_byte(&mut self) -> Option<u8> {
-        self.input.next().and_then(|b| Some(b.unwrap()))
+        let byte = self.input.next().map(|b| b.unwrap());
+        match byte {
+            Some(b'\n') => {
+                self.line_num += 1;
+                self.col_num = 0;
+                self.lines.push(Vec::new());
+            }
+            Some(b) => {
+                self.col_num += 1;
+                self.lines.last_mut().unwrap().push(b);
+            }
+            None => (),
+        }
+        byte
     }
 
     /**
@@ -50,10 +91,7 @@ impl<R: Read> Tokenizer<R> {
     fn peek_byte(&mut self) -> Result<u8, SunError> {
         match self.input.peek() {
             Some(Ok(byte)) => Ok(*byte),
-            Some(_) => Err(SunError::TokenizerError(format!(
-                "failed to peek byte at line {}",
-                self.line_num
-            ))),
+            Some(_) => Err(SunError::TokenizerError("failed to peek byte".to_string())),
             None => Ok(b'\0'),
         }
     }
@@ -89,13 +127,25 @@ impl<R: Read> Tokenizer<R> {
             + `Token`: 返回变量名
     */
     fn read_name(&mut self, first: u8) -> Result<Token, SunError> {
+        // `r"..."` 为原始字符串
+        if first == b'r' && self.peek_byte()? == b'"' {
+            self.read_byte();
+            return self.read_raw_string(b'"');
+        }
+        // `f"..."` 为 f-string
+        if first == b'f' && self.peek_byte()? == b'"' {
+            self.read_byte();
+            self.formats.push((self.start, 0));
+            self.format_part = true;
+            return Ok(Token::FormatStart);
+        }
         let mut s = String::new();
         s.push(first as char);
 
         loop {
             let ch = self.peek_byte()? as char;
             if ch.is_alphanumeric() || ch == '_' {
-                s.push(ch as char);
+                s.push(ch);
                 self.read_byte();
             } else {
                 break;
@@ -108,83 +158,213 @@ impl<R: Read> Tokenizer<R> {
             "false" => Token::False,
             "true" => Token::True,
             "fn" => Token::DefFunction,
+            "class" => Token::DefClass,
             "if" => Token::If,
             "else" => Token::Else,
             "while" => Token::Loop,
+            "for" => Token::For,
+            "in" => Token::In,
             "nil" => Token::Nil,
             "break" => Token::Break,
             "continue" => Token::Continue,
+            "let" => Token::Let,
+            "try" => Token::Try,
+            "catch" => Token::Catch,
+            "match" => Token::Match,
             _ => Token::Name(s),
         };
         Ok(res)
     }
 
     /**
-        获取下一个数字
+        获取下一个数字，支持 `0x` `0b` `0o` 前缀、指数和分隔数字的 `_`
         + `para`:
             + `first`: `char` 第一个字符
         + `return`:
             + `Token`: 数字 `Token`
     */
     fn read_number(&mut self, first: u8) -> Result<Token, SunError> {
-        let mut n = (first - b'0') as i64;
-        loop {
-            let ch = self.peek_byte()?;
-            if let Some(d) = char::to_digit(ch as char, 10) {
-                n = n * 10 + d as i64;
-                self.read_byte();
-            } else if ch == b'.' {
+        if first == b'0' {
+            let radix = match self.peek_byte()? {
+                b'x' | b'X' => Some(16),
+                b'b' | b'B' => Some(2),
+                b'o' | b'O' => Some(8),
+                _ => None,
+            };
+            if let Some(radix) = radix {
                 self.read_byte();
-                return self.read_number_fraction(n);
-            } else {
-                break;
+                return self.read_radix_number(radix);
             }
         }
+        let mut s = String::from(first as char);
+        self.read_digits(&mut s, 10);
+        if self.peek_byte()? == b'.' {
+            self.read_byte();
+            // `0..10` 中的 `..` 和 `1.add(2)` 中的 `.` 不是小数点
+            match self.peek_byte()? {
+                b'.' => {
+                    self.read_byte();
+                    self.pending = Some((Token::DotDot, (self.line_num, self.col_num - 1)));
+                    return self.parse_number(&s);
+                }
+                ch if ch.is_ascii_alphabetic() || ch == b'_' => {
+                    self.pending = Some((Token::Dot, (self.line_num, self.col_num)));
+                    return self.parse_number(&s);
+                }
+                _ => (),
+            }
+            s.push('.');
+            return self.read_number_fraction(s);
+        }
+        self.read_exponent(&mut s)?;
+        self.check_number_end(10)?;
+        self.parse_number(&s)
+    }
 
-        // check if another .
-        let fch = self.peek_byte()?;
-        if (fch as char).is_alphabetic() {
-            return Err(SunError::NumberError(format!(
-                "alphabetic in number at line {}",
-                self.line_num
-            )));
-        } else if fch == b'.' {
-            return Err(SunError::NumberError(format!(
-                "more than one `.` in number at line {}",
-                self.line_num
-            )));
+    /**
+        获取下一个浮点数的小数部分和指数部分
+        + `para`:
+            + `s`: `String` 已读取的整数部分和小数点
+        + `return`:
+            + `Token`: 浮点数 `Token`
+    */
+    fn read_number_fraction(&mut self, mut s: String) -> Result<Token, SunError> {
+        if self.read_digits(&mut s, 10) > 0 {
+            self.read_exponent(&mut s)?;
+            self.check_number_end(10)?;
+        }
+        if self.peek_byte()? == b'.' {
+            let e = SunError::NumberError("more than one `.` in number".to_string());
+            let span = self.span_at(self.col_num + 1);
+            return Err(self.diagnose(e, span, Some("a number can contain only one `.`")));
         }
+        self.parse_number(&s)
+    }
 
-        Ok(Token::Number(n as f64))
+    /**
+        读取数字的指数部分 `e[+-]digits`
+        + `para`:
+            + `s`: `String` 已读取的数字
+    */
+    fn read_exponent(&mut self, s: &mut String) -> Result<(), SunError> {
+        if !matches!(self.peek_byte()?, b'e' | b'E') {
+            return Ok(());
+        }
+        self.read_byte();
+        s.push('e');
+        if let sign @ (b'+' | b'-') = self.peek_byte()? {
+            self.read_byte();
+            s.push(sign as char);
+        }
+        if self.read_digits(s, 10) == 0 {
+            let e = SunError::NumberError("missing digits in exponent".to_string());
+            let span = self.span_at(self.col_num + 1);
+            return Err(self.diagnose(e, span, Some("write the exponent like `1e-9`")));
+        }
+        Ok(())
     }
 
     /**
-        获取下一个浮点数
+        获取 `0x` `0b` `0o` 前缀之后的整数
         + `para`:
-            + `i`: `i64` 浮点数的整数部分
+            + `radix`: `u32` 进制
         + `return`:
-            + `Token`: 浮点数 `Token`
+            + `Token`: 数字 `Token`
+    */
+    fn read_radix_number(&mut self, radix: u32) -> Result<Token, SunError> {
+        let col = self.start.1;
+        let mut s = String::new();
+        // 前缀之后可以紧跟 `_`，如 `0x_FF`
+        while self.peek_byte()? == b'_' {
+            self.read_byte();
+        }
+        if self.read_digits(&mut s, radix) == 0 {
+            let e = SunError::NumberError("missing digits after base prefix".to_string());
+            let span = self.span_at(self.col_num + 1);
+            return Err(self.diagnose(e, span, None));
+        }
+        self.check_number_end(radix)?;
+        match i64::from_str_radix(&s, radix) {
+            Ok(n) => Ok(Token::Integer(n)),
+            Err(_) => Err(self.integer_too_large(col)),
+        }
+    }
+
+    /**
+        读取连续的数字，跳过数字之间的 `_`
+        + `para`:
+            + `s`: `String` 读取结果追加到 `s`
+            + `radix`: `u32` 进制
+        + `return`:
+            + `usize`: 读取的数字个数
     */
-    fn read_number_fraction(&mut self, i: i64) -> Result<Token, SunError> {
-        let mut n: i64 = 0;
-        let mut x: f64 = 1.0;
+    fn read_digits(&mut self, s: &mut String, radix: u32) -> usize {
+        let mut count = 0;
+        let mut last_digit = s.ends_with(|ch: char| ch.is_digit(radix));
         loop {
-            let ch = self.peek_byte()?;
-            if ch == b'.' {
-                return Err(SunError::NumberError(format!(
-                    "more than one `.` in number at line {}",
-                    self.line_num
-                )));
-            }
-            if let Some(d) = char::to_digit(ch as char, 10) {
-                n = n * 10 + d as i64;
-                x *= 10.0;
-                self.read_byte();
-            } else {
+            let ch = self.peek_byte().unwrap_or(b'\0') as char;
+            if ch.is_digit(radix) {
+                s.push(ch);
+                count += 1;
+                last_digit = true;
+            } else if ch != '_' || !last_digit {
                 break;
             }
+            self.read_byte();
+        }
+        count
+    }
+
+    /**
+        检查数字之后是否紧跟字母或数字
+        + `para`:
+            + `radix`: `u32` 进制
+    */
+    fn check_number_end(&mut self, radix: u32) -> Result<(), SunError> {
+        let ch = self.peek_byte()? as char;
+        if !ch.is_ascii_alphanumeric() && ch != '_' {
+            return Ok(());
         }
-        Ok(Token::Number(i as f64 + n as f64 / x))
+        let (e, help) = if ch.is_ascii_digit() {
+            (format!("invalid digit `{ch}` for base {radix}"), None)
+        } else {
+            (
+                "alphabetic in number".to_string(),
+                Some("a name can't start with a digit"),
+            )
+        };
+        let span = self.span_at(self.col_num + 1);
+        Err(self.diagnose(SunError::NumberError(e), span, help))
+    }
+
+    /**
+        将去掉 `_` 的十进制数字转换为整数，带有小数点或指数时转换为最接近的浮点数
+        + `para`:
+            + `s`: `&str` 数字
+        + `return`:
+            + `Token`: 数字 `Token`
+    */
+    fn parse_number(&mut self, s: &str) -> Result<Token, SunError> {
+        if s.contains(['.', 'e']) {
+            let value = s
+                .parse::<f64>()
+                .map_err(|e| SunError::NumberError(format!("{e} `{s}`")))?;
+            return Ok(Token::Float(value));
+        }
+        match s.parse::<i64>() {
+            Ok(n) => Ok(Token::Integer(n)),
+            Err(_) => Err(self.integer_too_large(self.start.1)),
+        }
+    }
+
+    /**
+        超出 `Integer` 范围的整数错误，位置为数字的开始
+    */
+    fn integer_too_large(&mut self, col: u64) -> SunError {
+        let e = SunError::NumberError("integer is too large".to_string());
+        let span = self.span_at(col);
+        let help = "integers must fit in 64 bits, add `.0` to write a float";
+        self.diagnose(e, span, Some(help))
     }
 
     /**
@@ -192,101 +372,339 @@ impl<R: Read> Tokenizer<R> {
         + `para`:
             + `quote`: `char` 字符串的开始符
         + `return`:
-            `Token`: 字符串 `Token`
+            + `Token`: 字符串 `Token`
     */
     fn read_string(&mut self, quote: u8) -> Result<Token, SunError> {
+        // `""` 之后紧跟引号时为三引号字符串
+        if self.peek_byte()? == quote {
+            self.read_byte();
+            if self.peek_byte()? != quote {
+                return Ok(Token::String(Vec::new()));
+            }
+            self.read_byte();
+            return self.read_long_string(quote);
+        }
         let mut s = Vec::new();
         loop {
-            match self.read_byte().ok_or(SunError::TokenizerError(format!(
-                "read char failed at line {}",
-                self.line()
-            )))? {
-                b'\n' => {
-                    return Err(SunError::SymbolError(format!(
-                        "unfinished string at line {}",
-                        self.line()
-                    )))
+            match self.read_byte() {
+                None | Some(b'\n') => {
+                    let help = "add the closing quote before the end of the line, \
+                        or use `\"\"\"` for a multi-line string";
+                    return Err(self.unfinished_string(help));
                 }
-                ch if ch == quote => break,
-                ch => {
-                    s.push(ch);
+                Some(b'\\') => self.read_escape(&mut s)?,
+                Some(ch) if ch == quote => break,
+                Some(ch) => s.push(ch),
+            }
+        }
+        Ok(Token::String(s))
+    }
+
+    /**
+        获取下一个三引号字符串，可以跨越多行
+        + `para`:
+            + `quote`: `u8` 字符串的引号
+        + `return`:
+            + `Token`: 字符串 `Token`
+    */
+    fn read_long_string(&mut self, quote: u8) -> Result<Token, SunError> {
+        let mut s = Vec::new();
+        loop {
+            match self.read_byte() {
+                None => return Err(self.unfinished_string("add the closing `\"\"\"`")),
+                Some(b'\\') => self.read_escape(&mut s)?,
+                Some(ch) if ch == quote => {
+                    // 连续三个引号结束字符串，不足三个时作为字符串的内容
+                    let mut count = 1;
+                    while count < 3 && self.peek_byte()? == quote {
+                        self.read_byte();
+                        count += 1;
+                    }
+                    if count == 3 {
+                        break;
+                    }
+                    s.extend(std::iter::repeat_n(quote, count));
+                }
+                Some(ch) => s.push(ch),
+            }
+        }
+        Ok(Token::String(s))
+    }
+
+    /**
+        获取下一个原始字符串，不处理转义字符
+        + `para`:
+            + `quote`: `u8` 字符串的引号
+        + `return`:
+            + `Token`: 字符串 `Token`
+    */
+    fn read_raw_string(&mut self, quote: u8) -> Result<Token, SunError> {
+        let mut s = Vec::new();
+        loop {
+            match self.read_byte() {
+                None | Some(b'\n') => {
+                    let help = "add the closing quote before the end of the line";
+                    return Err(self.unfinished_string(help));
                 }
+                Some(ch) if ch == quote => break,
+                Some(ch) => s.push(ch),
             }
         }
         Ok(Token::String(s))
     }
 
+    /**
+        获取 f-string 的下一段字符串，遇到嵌入表达式的 `{` 或结束的引号时停止，
+        `{{` 和 `}}` 表示字符 `{` 和 `}`
+        + `return`:
+            + `Token`: 字符串部分、嵌入表达式开始的 `{` 或 f-string 的结束
+    */
+    fn read_format_part(&mut self) -> Result<Token, SunError> {
+        let mut s = Vec::new();
+        let token = loop {
+            match self.read_byte() {
+                None | Some(b'\n') => {
+                    // 错误位置为 f-string 的开始
+                    self.start = self.formats.last().unwrap().0;
+                    let help = "add the closing quote before the end of the line";
+                    return Err(self.unfinished_string(help));
+                }
+                Some(b'\\') => self.read_escape(&mut s)?,
+                Some(b'"') => {
+                    self.formats.pop();
+                    self.format_part = false;
+                    break Token::FormatEnd;
+                }
+                Some(ch @ (b'{' | b'}')) if self.peek_byte()? == ch => {
+                    self.read_byte();
+                    s.push(ch);
+                }
+                Some(b'{') => {
+                    self.format_part = false;
+                    break Token::CurL;
+                }
+                Some(b'}') => {
+                    let e = SunError::SymbolError("single `}` in f-string".to_string());
+                    let span = self.span_at(self.col_num);
+                    return Err(self.diagnose(e, span, Some("use `}}` for a literal `}`")));
+                }
+                Some(ch) => s.push(ch),
+            }
+        };
+        if s.is_empty() {
+            self.start = (self.line_num, self.col_num);
+            Ok(token)
+        } else {
+            self.pending = Some((token, (self.line_num, self.col_num)));
+            Ok(Token::FormatPart(s))
+        }
+    }
+
+    /**
+        处理 `\\` 之后的转义字符，将转义结果追加到 `s`
+        + `para`:
+            + `s`: `Vec<u8>` 正在读取的字符串
+        + `return`:
+            + `Result<(), SunError>`: 未知的转义字符或非法的 Unicode 转义
+    */
+    fn read_escape(&mut self, s: &mut Vec<u8>) -> Result<(), SunError> {
+        let col = self.col_num;
+        let byte = match self.read_byte() {
+            Some(b'n') => b'\n',
+            Some(b't') => b'\t',
+            Some(b'r') => b'\r',
+            Some(b'0') => b'\0',
+            Some(b'\\') => b'\\',
+            Some(b'"') => b'"',
+            Some(b'\'') => b'\'',
+            Some(b'u') => {
+                let ch = self.read_unicode_escape(col)?;
+                let mut buf = [0; 4];
+                s.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+                return Ok(());
+            }
+            other => {
+                let e = SunError::SymbolError(format!(
+                    "unknown escape `\\{}`",
+                    other.map_or(String::new(), |b| (b as char).to_string())
+                ));
+                let span = self.span_at(col);
+                let help =
+                    "valid escapes are `\\n` `\\t` `\\r` `\\0` `\\\\` `\\\"` `\\'` `\\u{...}`, \
+                    or use a raw string `r\"...\"`";
+                return Err(self.diagnose(e, span, Some(help)));
+            }
+        };
+        s.push(byte);
+        Ok(())
+    }
+
+    /**
+        处理 `\\u{...}` 形式的 Unicode 转义
+        + `para`:
+            + `col`: `u64` `\\` 所在的列
+        + `return`:
+            + `char`: 转义得到的字符
+    */
+    fn read_unicode_escape(&mut self, col: u64) -> Result<char, SunError> {
+        let mut code = None;
+        if self.peek_byte()? == b'{' {
+            self.read_byte();
+            let mut digits = String::new();
+            while digits.len() < 6 && self.peek_byte()?.is_ascii_hexdigit() {
+                digits.push(self.read_byte().unwrap() as char);
+            }
+            if self.peek_byte()? == b'}' && !digits.is_empty() {
+                self.read_byte();
+                code = u32::from_str_radix(&digits, 16).ok();
+            }
+        }
+        match code.and_then(char::from_u32) {
+            Some(ch) => Ok(ch),
+            None => {
+                let e = SunError::SymbolError("invalid unicode escape".to_string());
+                let span = self.span_at(col);
+                let help =
+                    "use `\\u{XXXX}` with 1 to 6 hexadecimal digits of a unicode scalar value";
+                Err(self.diagnose(e, span, Some(help)))
+            }
+        }
+    }
+
+    /**
+        未结束的字符串错误，位置为字符串的开始
+    */
+    fn unfinished_string(&mut self, help: &str) -> SunError {
+        let e = SunError::SymbolError("unfinished string".to_string());
+        let span = self.start_span();
+        self.diagnose(e, span, Some(help))
+    }
+
     /**
         跳过注释
     */
     fn read_comment(&mut self) {
-        match self.read_byte() {
-            None => (),
-            Some(_) => loop {
-                let ch = self.read_byte().unwrap();
-                if ch == b'\n' || ch == b'\0' {
-                    break;
-                }
-            },
+        while let Some(ch) = self.read_byte() {
+            if ch == b'\n' || ch == b'\0' {
+                break;
+            }
         }
     }
 
     /**
         获取下一个 `Token`
         + `return`:
-            + `Token`: 下一个 `Token`
+            + `Result<Token, SunError>`: 下一个 `Token` 或词法错误
     */
-    fn read_token(&mut self) -> Token {
+    fn read_token(&mut self) -> Result<Token, SunError> {
+        if let Some((token, start)) = self.pending.take() {
+            self.start = start;
+            return Ok(token);
+        }
+        if self.format_part {
+            self.start = (self.line_num, self.col_num + 1);
+            return self.read_format_part();
+        }
         if let Some(ch) = self.read_byte() {
-            let res: Result<Token, SunError> = match ch {
-                b'\n' => {
-                    self.line_num += 1;
-                    Ok(self.read_token())
-                }
-                b'\r' | b'\t' | b' ' => Ok(self.read_token()),
-                b'+' => Ok(Token::Add),
-                b'-' => self.read_2char(b'>', Token::Return, Token::Sub),
-                b'*' => Ok(Token::Mul),
-                b'%' => Ok(Token::Mod),
-                b'^' => Ok(Token::Xor),
+            self.start = (self.line_num, self.col_num);
+            match ch {
+                b'\n' | b'\r' | b'\t' | b' ' => self.read_token(),
+                b'+' => self.read_2char(b'=', Token::AddAssign, Token::Add),
+                b'-' => match self.peek_byte()? {
+                    b'>' => {
+                        self.read_byte();
+                        Ok(Token::Return)
+                    }
+                    b'=' => {
+                        self.read_byte();
+                        Ok(Token::SubAssign)
+                    }
+                    _ => Ok(Token::Sub),
+                },
+                b'*' => self.read_2char(b'=', Token::MulAssign, Token::Mul),
+                b'%' => self.read_2char(b'=', Token::ModAssign, Token::Mod),
+                // `//` 为注释，向下取整的除法使用 `~/`
+                b'~' => match self.peek_byte() {
+                    Ok(b'/') => {
+                        self.read_byte();
+                        self.read_2char(b'=', Token::FloorDivAssign, Token::FloorDiv)
+                    }
+                    Ok(other) => {
+                        let e = SunError::SymbolError(format!("unexpected byte `{other}`"));
+                        let span = self.start_span();
+                        Err(self.diagnose(e, span, Some("use `~/` for floor division")))
+                    }
+                    Err(e) => Err(e),
+                },
+                b'^' => match self.peek_byte()? {
+                    b'^' => {
+                        self.read_byte();
+                        Ok(Token::Xor)
+                    }
+                    b'=' => {
+                        self.read_byte();
+                        Ok(Token::PowAssign)
+                    }
+                    _ => Ok(Token::Pow),
+                },
                 b'@' => Ok(Token::Import),
                 b'(' => Ok(Token::ParL),
                 b')' => Ok(Token::ParR),
-                b'{' => Ok(Token::CurL),
-                b'}' => Ok(Token::CurR),
+                b'{' => {
+                    if let Some((_, depth)) = self.formats.last_mut() {
+                        *depth += 1;
+                    }
+                    Ok(Token::CurL)
+                }
+                b'}' => {
+                    // 闭合嵌入表达式的 `}` 之后继续读取 f-string 的字符串部分
+                    match self.formats.last_mut() {
+                        Some((_, 0)) => self.format_part = true,
+                        Some((_, depth)) => *depth -= 1,
+                        None => (),
+                    }
+                    Ok(Token::CurR)
+                }
                 b'[' => Ok(Token::SquL),
                 b']' => Ok(Token::SquR),
                 b',' => Ok(Token::Comma),
                 b';' => Ok(Token::Semi),
-                b'|' => match self.peek_byte() {
-                    Ok(b'|') => {
+                b'?' => Ok(Token::Question),
+                b'|' => self.read_2char(b'|', Token::Or, Token::Pipe),
+                b'&' => match self.peek_byte() {
+                    Ok(b'&') => {
                         self.read_byte();
-                        Ok(Token::Or)
+                        Ok(Token::And)
+                    }
+                    Ok(other) => {
+                        let e = SunError::SymbolError(format!("unexpected byte `{other}`"));
+                        let span = self.start_span();
+                        Err(self.diagnose(e, span, Some("use `&&` for logical and")))
                     }
-                    Ok(other) => Err(SunError::SymbolError(format!(
-                        "unexpected byte `{other}` at line {}",
-                        self.line()
-                    ))),
                     Err(e) => Err(e),
                 },
-                b'&' => match self.peek_byte() {
-                    Ok(b'&') => {
+                b'=' => match self.peek_byte() {
+                    Ok(b'=') => {
                         self.read_byte();
-                        Ok(Token::And)
+                        Ok(Token::Eq)
                     }
-                    Ok(other) => Err(SunError::SymbolError(format!(
-                        "unexpected byte `{other}` at line {}",
-                        self.line()
-                    ))),
+                    Ok(b'>') => {
+                        self.read_byte();
+                        Ok(Token::FatArrow)
+                    }
+                    Ok(_) => Ok(Token::Assign),
                     Err(e) => Err(e),
                 },
-                b'=' => self.read_2char(b'=', Token::Eq, Token::Assign),
                 b'!' => self.read_2char(b'=', Token::NotEq, Token::Not),
                 b':' => self.read_2char(b':', Token::DoubleColon, Token::Colon),
                 b'<' => self.read_2char(b'=', Token::Le, Token::Less),
                 b'>' => self.read_2char(b'=', Token::Ge, Token::Greater),
                 b'.' => match self.peek_byte() {
-                    Ok(b'0'..=b'9') => self.read_number_fraction(0),
+                    Ok(b'0'..=b'9') => self.read_number_fraction(String::from("0.")),
+                    Ok(b'.') => {
+                        self.read_byte();
+                        self.read_2char(b'.', Token::Ellipsis, Token::DotDot)
+                    }
                     Ok(_) => Ok(Token::Dot),
                     Err(e) => Err(e),
                 },
@@ -294,7 +712,11 @@ impl<R: Read> Tokenizer<R> {
                     Ok(b'/') => {
                         self.read_byte();
                         self.read_comment();
-                        Ok(self.read_token())
+                        self.read_token()
+                    }
+                    Ok(b'=') => {
+                        self.read_byte();
+                        Ok(Token::DivAssign)
                     }
                     Ok(_) => Ok(Token::Div),
                     Err(e) => Err(e),
@@ -303,44 +725,97 @@ impl<R: Read> Tokenizer<R> {
                 b'0'..=b'9' => self.read_number(ch),
                 b'A'..=b'Z' | b'a'..=b'z' | b'_' => self.read_name(ch),
                 b'\0' => Ok(Token::Eos),
-                byte => Err(SunError::SymbolError(format!(
-                    "invalid char {} at line {}",
-                    byte as char, self.line_num
-                ))),
-            };
-            match res {
-                Ok(r) => r,
-                Err(e) => error_output(e),
+                byte => {
+                    let e = SunError::SymbolError(format!("invalid char {}", byte as char));
+                    let span = self.start_span();
+                    Err(self.diagnose(e, span, None))
+                }
             }
         } else {
-            Token::Eos
+            // 源码以换行结尾时，结束符位于最后一行的末尾
+            self.start = match self.lines.len() {
+                len if len > 1 && self.col_num == 0 => {
+                    (self.line_num - 1, self.lines[len - 2].len() as u64 + 1)
+                }
+                _ => (self.line_num, self.col_num + 1),
+            };
+            Ok(Token::Eos)
         }
     }
+
+    /**
+        正在读取的 `Token` 的位置
+    */
+    fn start_span(&self) -> Span {
+        Span::new(self.name.clone(), self.start.0, self.start.1)
+    }
+
+    /**
+        当前行第 `col` 列的位置
+    */
+    fn span_at(&self, col: u64) -> Span {
+        Span::new(self.name.clone(), self.line_num, col)
+    }
+
+    /**
+        为错误附上源码诊断信息
+        + `para`:
+            + `error`: `SunError` 原始错误
+            + `span`: `Span` 出错的位置
+            + `help`: `Option<&str>` 帮助信息
+        + `return`:
+            + `SunError`: 附带诊断信息的错误
+    */
+    pub fn diagnose(&mut self, error: SunError, span: Span, help: Option<&str>) -> SunError {
+        // 出错位置所在的行可能还未读完
+        while span.line == self.line_num {
+            if let None | Some(b'\n') = self.read_byte() {
+                break;
+            }
+        }
+        let line = span
+            .line
+            .checked_sub(1)
+            .and_then(|i| self.lines.get(i as usize));
+        let source = match line {
+            Some(line) => String::from_utf8_lossy(line).trim_end().to_string(),
+            None => String::new(),
+        };
+        let mut diagnostic = Diagnostic::new(span, source);
+        if let Some(help) = help {
+            diagnostic = diagnostic.with_help(help);
+        }
+        SunError::SourceError(Box::new(error), diagnostic)
+    }
 }
 
 /**
     Tokenizer 的迭代器
 */
 impl<R: Read> Iterator for Tokenizer<R> {
-    type Item = Token;
+    type Item = Result<Token, SunError>;
 
     /// 向前获取一个 `Token`
     fn next(&mut self) -> Option<Self::Item> {
         if self.ahead == Token::Eos {
-            match self.read_token() {
-                Token::Eos => None,
-                t => {
-                    if self.check == true {
+            let token = self.read_token();
+            self.span = self.start_span();
+            match token {
+                Ok(Token::Eos) => None,
+                Ok(t) => {
+                    if self.check {
                         debug_output(&t, false);
                     }
-                    Some(t)
+                    Some(Ok(t))
                 }
+                Err(e) => Some(Err(e)),
             }
         } else {
-            if self.check == true {
+            if self.check {
                 debug_output(&self.ahead, false);
             }
-            Some(replace(&mut self.ahead, Token::Eos))
+            self.span = self.ahead_span.clone();
+            Some(Ok(replace(&mut self.ahead, Token::Eos)))
         }
     }
 }
@@ -350,17 +825,29 @@ impl<R: Read> Iterator for Tokenizer<R> {
 */
 impl<R: Read> Tokenizer<R> {
     /// 向前查看一个 `Token`，不改变迭代器状态
-    pub fn peek(&mut self) -> &Token {
+    pub fn peek(&mut self) -> Result<&Token, SunError> {
         if self.ahead == Token::Eos {
-            self.ahead = self.read_token();
+            self.ahead = self.read_token()?;
+            self.ahead_span = self.start_span();
         }
-        &self.ahead
+        Ok(&self.ahead)
     }
 
     /// 获取行号
     pub fn line(&self) -> u64 {
         self.line_num
     }
+
+    /// 获取最近一个 `Token` 的位置
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    /// 获取下一个 `Token` 的位置，不改变迭代器状态
+    pub fn peek_span(&mut self) -> Result<Span, SunError> {
+        self.peek()?;
+        Ok(self.ahead_span.clone())
+    }
 }
 
 #[cfg(test)]
@@ -369,14 +856,127 @@ mod tests {
 
     use super::Tokenizer;
     use std::fs::File;
+    use sun_core::container::SunValue;
 
     #[test]
     fn test_token_1() {
-        let mut tokenizer = Tokenizer::new(File::open("test/file/1.sun").unwrap(), false);
-        assert_eq!(tokenizer.next(), Some(Token::Name("print".to_string())));
-        assert_eq!(tokenizer.peek(), &Token::ParL);
+        let mut tokenizer = Tokenizer::new(File::open("test/file/1.sun").unwrap(), "1.sun", false);
+        assert_eq!(tokenizer.next(), Some(Ok(Token::Name("print".to_string()))));
+        assert_eq!(tokenizer.peek(), Ok(&Token::ParL));
         tokenizer.next();
-        assert_eq!(tokenizer.next(), Some(Token::Number(10.2)));
-        assert_eq!(tokenizer.next(), Some(Token::ParR));
+        assert_eq!(tokenizer.next(), Some(Ok(Token::Float(10.2))));
+        assert_eq!(tokenizer.next(), Some(Ok(Token::ParR)));
+    }
+
+    #[test]
+    fn test_comment_at_eof() {
+        let tokens = Tokenizer::new("a = 1 // 0".as_bytes(), "<test>", false)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Name("a".to_string()),
+                Token::Assign,
+                Token::Integer(1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_string() {
+        let string = |src: &str| {
+            let mut tokenizer = Tokenizer::new(src.as_bytes(), "<test>", false);
+            match tokenizer.next() {
+                Some(Ok(Token::String(s))) => Ok(String::from_utf8(s).unwrap()),
+                Some(Err(e)) => Err(e.kind()),
+                other => panic!("unexpected token {other:?}"),
+            }
+        };
+        assert_eq!(
+            string(r#""a\tb\n\"c\"\\""#),
+            Ok("a\tb\n\"c\"\\".to_string())
+        );
+        assert_eq!(string(r#""\u{4E2D}\u{6587}""#), Ok("中文".to_string()));
+        assert_eq!(string(r#""""#), Ok(String::new()));
+        assert_eq!(
+            string("\"\"\"line 1\n\"line\" \"\"2\"\"\""),
+            Ok("line 1\n\"line\" \"\"2".to_string())
+        );
+        assert_eq!(
+            string(r#"r"C:\new\table""#),
+            Ok(r"C:\new\table".to_string())
+        );
+        assert_eq!(string(r#""\q""#), Err("SymbolError"));
+        assert_eq!(string(r#""\u{110000}""#), Err("SymbolError"));
+        assert_eq!(string("\"abc\ndef\""), Err("SymbolError"));
+        assert_eq!(string("\"\"\"abc"), Err("SymbolError"));
+    }
+
+    #[test]
+    fn test_format_string() {
+        let src = r#"f"a{x + 1}{{{t["k"]}}}""#;
+        let tokens = Tokenizer::new(src.as_bytes(), "<test>", false)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::FormatStart,
+                Token::FormatPart(b"a".to_vec()),
+                Token::CurL,
+                Token::Name("x".to_string()),
+                Token::Add,
+                Token::Integer(1),
+                Token::CurR,
+                Token::FormatPart(b"{".to_vec()),
+                Token::CurL,
+                Token::Name("t".to_string()),
+                Token::SquL,
+                Token::String(b"k".to_vec()),
+                Token::SquR,
+                Token::CurR,
+                Token::FormatPart(b"}".to_vec()),
+                Token::FormatEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_number() {
+        let number = |src: &str| {
+            let mut tokenizer = Tokenizer::new(src.as_bytes(), "<test>", false);
+            match tokenizer.next() {
+                Some(Ok(Token::Integer(n))) => Ok(SunValue::from(n)),
+                Some(Ok(Token::Float(n))) => Ok(SunValue::from(n)),
+                Some(Err(e)) => Err(e.kind()),
+                other => panic!("unexpected token {other:?}"),
+            }
+        };
+        assert_eq!(number("0xFF"), Ok(SunValue::from(255)));
+        assert_eq!(number("0b1010"), Ok(SunValue::from(10)));
+        assert_eq!(number("0o17"), Ok(SunValue::from(15)));
+        assert_eq!(number("0x_ff_ff"), Ok(SunValue::from(65535)));
+        assert_eq!(number("1e-9"), Ok(SunValue::from(1e-9)));
+        assert_eq!(number("6.02E23"), Ok(SunValue::from(6.02e23)));
+        assert_eq!(number("1_000_000"), Ok(SunValue::from(1_000_000)));
+        assert_eq!(number("0.1"), Ok(SunValue::from(0.1)));
+        assert_eq!(number(".5"), Ok(SunValue::from(0.5)));
+        assert_eq!(number("2.5e+3"), Ok(SunValue::from(2500.0)));
+        assert_eq!(
+            number("9007199254740993"),
+            Ok(SunValue::from(9007199254740993))
+        );
+        assert_eq!(
+            number("9007199254740993.0"),
+            Ok(SunValue::from(9007199254740992.0))
+        );
+        assert_eq!(number("9223372036854775808"), Err("NumberError"));
+        assert_eq!(number("1e"), Err("NumberError"));
+        assert_eq!(number("0x"), Err("NumberError"));
+        assert_eq!(number("0b102"), Err("NumberError"));
+        assert_eq!(number("12abc"), Err("NumberError"));
+        assert_eq!(number("1.2.3"), Err("NumberError"));
+        assert_eq!(number("0x8000_0000_0000_0000"), Err("NumberError"));
     }
 }
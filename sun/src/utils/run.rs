@@ -1,10 +1,10 @@
 use crate::{parser::parser::ParseProto, utils::config::Config, vm::machine::VirtualMachine};
 use colorized::*;
 use std::fs::File;
-use std::io::{BufReader, Write};
+use std::io::{BufReader, Read, Write};
 use std::process;
 use std::{env, io};
-use sun_core::utils::SunError;
+use sun_core::utils::{log::error_output, SunError};
 
 /**
     sun 解释器程序运行的入口函数
@@ -18,40 +18,59 @@ pub fn run() {
             io::stdout().flush().expect("failed to flush stdout");
             let mut buf = String::new();
             match io::stdin().read_line(&mut buf) {
+                // 输入结束
+                Ok(0) => break,
+                Ok(_) if buf.trim().is_empty() => continue,
                 Ok(_) => {
-                    let buf = buf.as_bytes();
-                    vm.run(&ParseProto::new(
-                        buf,
-                        config.check_tokenizer,
-                        config.check_parser,
-                        config.check_command,
-                    ));
+                    // 交互模式下出错后继续接收输入
+                    if let Err(e) = execute(&mut vm, buf.as_bytes(), "<stdin>", &config) {
+                        error_output(e);
+                    }
                 }
                 Err(e) => {
                     eprintln!("{}", SunError::InputError(e.to_string()));
-                    process::exit(0);
+                    process::exit(1);
                 }
             }
         },
         2 => match File::open(&args[1]) {
-            Ok(f) => vm.run(&ParseProto::new(
-                BufReader::new(f),
-                config.check_tokenizer,
-                config.check_parser,
-                config.check_command,
-            )),
+            Ok(f) => {
+                if let Err(e) = execute(&mut vm, BufReader::new(f), &args[1], &config) {
+                    error_output(e);
+                    process::exit(1);
+                }
+            }
             Err(_) => {
                 eprintln!(
                     "{}",
                     SunError::InputError("failed to find target file".to_string())
                 );
-                process::exit(0);
+                process::exit(1);
             }
         },
         _ => {}
     }
 }
 
+/**
+    对输入进行语法分析并在虚拟机中运行，`name` 为源文件名
+*/
+fn execute<T: Read>(
+    vm: &mut VirtualMachine,
+    input: T,
+    name: &str,
+    config: &Config,
+) -> Result<(), SunError> {
+    let proto = ParseProto::new(
+        input,
+        name,
+        config.check_tokenizer,
+        config.check_parser,
+        config.check_command,
+    )?;
+    vm.run(&proto)
+}
+
 /**
     `get_config` 处理运行时的配置，捕获命令行参数，生成配置，同时将配置参数从命令行参数容器中去除
 
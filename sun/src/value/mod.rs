@@ -1,5 +1,7 @@
 pub mod sun_boolean;
 pub mod sun_function;
+pub mod sun_instance;
 pub mod sun_nil;
 pub mod sun_number;
+pub mod sun_string;
 pub mod sun_table;
@@ -3,7 +3,10 @@ use sun_core::{
     add_meta_methods,
     container::{Function, RustFunction, SunValue},
     meta::{
-        meta_methods::op::{AndAble, CompareAble, EqualAble, NotAble, OrAble, XorAble},
+        meta_methods::{
+            check_args, mismatch,
+            op::{AndAble, CompareAble, EqualAble, NotAble, OrAble, XorAble},
+        },
         OwnSunMeta, SunBase, SunMeta,
     },
     utils::SunPointer,
@@ -37,6 +40,12 @@ impl SunBoolean {
     }
 }
 
+impl Default for SunBoolean {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl OwnSunMeta for SunBoolean {
     fn get_meta(&self) -> &SunMeta {
         &self.meta
@@ -68,30 +77,30 @@ impl NotAble for SunBoolean {
 impl XorAble for SunBoolean {
     fn xor() -> Function {
         let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
             let arg_0 = args[0].get();
             let arg_1 = args[1].get();
-            let mut self_value = args[0].borrow_mut();
-            let res = match (arg_0, arg_1) {
+
+            match (&arg_0, &arg_1) {
                 (SunValue::Boolean(b1), SunValue::Boolean(b2)) => {
-                    let value = !(b1 == b2);
-                    *self_value = SunValue::from(value);
-                    vec![args[0].clone()]
+                    let value = b1 != b2;
+                    Ok(vec![SunPointer::new(SunValue::from(value))])
                 }
-                _ => Vec::new(),
-            };
-            res
+                _ => Err(mismatch("^^", &arg_0, &arg_1)),
+            }
         };
         Function::from(f as RustFunction)
     }
 }
 
 impl EqualAble for SunBoolean {
+    /// 与其他类型的值不相等
     fn eq() -> Function {
-        compare_op_b!(==)
+        compare_op_b!(==, false)
     }
 
     fn noteq() -> Function {
-        compare_op_b!(!=)
+        compare_op_b!(!=, true)
     }
 }
 
@@ -117,19 +126,18 @@ impl CompareAble for SunBoolean {
 #[macro_export]
 macro_rules! double_op_b {
     ($op:tt) => {{
+        use sun_core::meta::meta_methods::{check_args, mismatch};
         let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
             let arg_0 = args[0].get();
             let arg_1 = args[1].get();
-            let mut self_value = args[0].borrow_mut();
-            let res = match (arg_0, arg_1) {
+            match (&arg_0, &arg_1) {
                 (SunValue::Boolean(b1), SunValue::Boolean(b2)) => {
-                    let value = b1 $op b2;
-                    *self_value = SunValue::from(value);
-                    vec![args[0].clone()]
+                    let value = *b1 $op *b2;
+                    Ok(vec![SunPointer::new(SunValue::from(value))])
                 }
-                _ => Vec::new(),
-            };
-            res
+                _ => Err(mismatch(stringify!($op), &arg_0, &arg_1)),
+            }
         };
         Function::from(f as RustFunction)
     }};
@@ -140,37 +148,45 @@ macro_rules! double_op_b {
 macro_rules! single_op_b {
     ($op:tt) => {{
         let f = |args: Vec<SunPointer>| {
-            let arg = args[0].get();
-            let mut self_value = args[0].borrow_mut();
-            let res = match arg {
-                SunValue::Boolean(b) => {
-                    *self_value = SunValue::from($op b);
-                    vec![args[0].clone()]
+            sun_core::meta::meta_methods::check_args(&args, 1)?;
+            match args[0].get() {
+                SunValue::Boolean(b) => Ok(vec![SunPointer::new(SunValue::from($op b))]),
+                other => {
+                    let e = sun_core::utils::SunError::TypeError(format!(
+                        "unsupported operand type for `{}`: `{}`",
+                        stringify!($op),
+                        other.get_name()
+                    ));
+                    Err(e)
                 }
-                _ => Vec::new(),
-            };
-            res
+            }
         };
         Function::from(f as RustFunction)
     }};
 }
 
-/// 批量处理 Bool 的比较操作符
+/// 批量处理 Bool 的比较操作符，`$mismatch`: 与其他类型比较时的结果，省略时抛出 `TypeError`
 #[macro_export]
 macro_rules! compare_op_b {
-    ($op:tt) => {{
+    ($op:tt) => {
+        $crate::compare_op_b!(@ $op, None)
+    };
+    ($op:tt, $mismatch:expr) => {
+        $crate::compare_op_b!(@ $op, Some($mismatch))
+    };
+    (@ $op:tt, $mismatch:expr) => {{
+        use sun_core::meta::meta_methods::{check_args, mismatch};
         let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
             let arg_0 = args[0].get();
             let arg_1 = args[1].get();
-            let res = match (arg_0, arg_1) {
-                (SunValue::Boolean(b1), SunValue::Boolean(b2)) => {
-                    let value = b1 $op b2;
-                    vec![SunPointer::new(SunValue::from(value))]
-                }
-                _ => Vec::new(),
+            let value = match (&arg_0, &arg_1, $mismatch) {
+                (SunValue::Boolean(b1), SunValue::Boolean(b2), _) => *b1 $op *b2,
+                (_, _, Some(value)) => value,
+                _ => return Err(mismatch(stringify!($op), &arg_0, &arg_1)),
             };
-            res
+            Ok(vec![SunPointer::new(SunValue::from(value))])
         };
-    Function::from(f as RustFunction)
+        Function::from(f as RustFunction)
     }};
 }
@@ -15,6 +15,12 @@ impl SunFunction {
     }
 }
 
+impl Default for SunFunction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl OwnSunMeta for SunFunction {
     fn get_meta(&self) -> &SunMeta {
         &self.meta
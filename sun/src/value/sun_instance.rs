@@ -0,0 +1,103 @@
+use std::rc::Rc;
+use sun_core::{
+    add_meta_methods,
+    container::{Function, RustFunction, SunValue},
+    meta::{
+        meta_methods::{check_args, container::IndexAble, op::EqualAble},
+        OwnSunMeta, SunBase, SunMeta,
+    },
+    utils::{SunError, SunPointer},
+};
+
+/// 脚本定义的类的实例共用的 `Class` 元数据，是所有脚本定义的类的基类
+#[derive(Clone, Debug)]
+pub struct SunInstance {
+    meta: SunMeta,
+}
+
+impl OwnSunMeta for SunInstance {
+    fn get_meta(&self) -> &SunMeta {
+        &self.meta
+    }
+
+    fn get_meta_mut(&mut self) -> &mut SunMeta {
+        &mut self.meta
+    }
+}
+
+impl SunInstance {
+    /// 新建 `Class` 元数据
+    pub fn new() -> SunInstance {
+        let mut meta = SunMeta::new("Class", SunBase::Object);
+        add_meta_methods!(
+            meta,
+            SunInstance,
+            ("index", index),
+            ("eq", eq),
+            ("noteq", noteq)
+        );
+        SunInstance { meta }
+    }
+}
+
+impl Default for SunInstance {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IndexAble for SunInstance {
+    /// 按属性名获取实例的属性
+    fn index() -> Function {
+        let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
+            let instance = args[0].borrow();
+            let c = match &*instance {
+                SunValue::Class(c) => c,
+                _ => {
+                    let e = SunError::ParaError("invalid parameters for get attribute".to_string());
+                    return Err(e);
+                }
+            };
+            let name = match &*args[1].borrow() {
+                name @ SunValue::String(_) => String::try_from(name)?,
+                other => {
+                    let e = SunError::KeyError(format!("`{other}` can't be an attribute name"));
+                    return Err(e);
+                }
+            };
+            match c.get_attribute(&name) {
+                Some(res) => Ok(vec![res]),
+                None => {
+                    let e = SunError::AttributeError(format!(
+                        "failed to find attribute `{name}` for type `{}`",
+                        c.get_name()
+                    ));
+                    Err(e)
+                }
+            }
+        };
+        Function::from(f as RustFunction)
+    }
+}
+
+impl EqualAble for SunInstance {
+    /// 没有定义 `eq` 方法的实例按引用比较
+    fn eq() -> Function {
+        let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
+            let res = Rc::ptr_eq(&args[0], &args[1]);
+            Ok(vec![SunPointer::new(SunValue::from(res))])
+        };
+        Function::from(f as RustFunction)
+    }
+
+    fn noteq() -> Function {
+        let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
+            let res = !Rc::ptr_eq(&args[0], &args[1]);
+            Ok(vec![SunPointer::new(SunValue::from(res))])
+        };
+        Function::from(f as RustFunction)
+    }
+}
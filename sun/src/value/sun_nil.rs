@@ -2,9 +2,12 @@ use sun_core::{
     add_meta_methods,
     container::{Function, RustFunction, SunValue},
     meta::{
-        meta_methods::op::{
-            AddAble, AndAble, CompareAble, ConjAble, DivAble, EqualAble, MulAble, NegAble, NotAble,
-            OrAble, RemAble, SubAble, XorAble,
+        meta_methods::{
+            check_args,
+            op::{
+                AddAble, AndAble, CompareAble, ConjAble, DivAble, EqualAble, MulAble, NegAble,
+                NotAble, OrAble, RemAble, SubAble, XorAble,
+            },
         },
         OwnSunMeta, SunBase, SunMeta,
     },
@@ -43,16 +46,24 @@ impl SunNil {
             ("and", and),
             ("or", or),
             ("xor", xor),
-            ("not", not)
+            ("not", not),
+            ("eq", eq),
+            ("noteq", noteq)
         );
         SunNil { meta }
     }
 }
 
+impl Default for SunNil {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// 批量处理 Nil 的运算符
 macro_rules! nil_op {
     () => {{
-        let f = |_: Vec<SunPointer>| vec![SunPointer::new(SunValue::Nil)];
+        let f = |_: Vec<SunPointer>| Ok(vec![SunPointer::new(SunValue::Nil)]);
         Function::from(f as RustFunction)
     }};
 }
@@ -60,7 +71,7 @@ macro_rules! nil_op {
 /// 批量处理 Nil 的比较运算符
 macro_rules! nil_compare {
     () => {{
-        let f = |_: Vec<SunPointer>| vec![SunPointer::new(SunValue::from(false))];
+        let f = |_: Vec<SunPointer>| Ok(vec![SunPointer::new(SunValue::from(false))]);
         Function::from(f as RustFunction)
     }};
 }
@@ -133,11 +144,21 @@ impl NotAble for SunNil {
 
 impl EqualAble for SunNil {
     fn eq() -> Function {
-        nil_compare!()
+        let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
+            let value = matches!(args[1].get(), SunValue::Nil);
+            Ok(vec![SunPointer::new(SunValue::from(value))])
+        };
+        Function::from(f as RustFunction)
     }
 
     fn noteq() -> Function {
-        nil_compare!()
+        let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
+            let value = !matches!(args[1].get(), SunValue::Nil);
+            Ok(vec![SunPointer::new(SunValue::from(value))])
+        };
+        Function::from(f as RustFunction)
     }
 }
 
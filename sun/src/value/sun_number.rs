@@ -1,18 +1,22 @@
-use crate::{compare_op, double_op, single_op};
+use crate::{compare_op, double_op};
 use colorized::*;
 use sun_core::{
     add_meta_methods,
     container::{Function, RustFunction, SunValue},
     meta::{
-        meta_methods::op::{
-            AddAble, CompareAble, DivAble, EqualAble, MulAble, NegAble, RemAble, SubAble,
+        meta_methods::{
+            check_args, mismatch,
+            op::{
+                AddAble, CompareAble, DivAble, EqualAble, FacAble, MulAble, NegAble, PowAble,
+                RemAble, SubAble,
+            },
         },
         OwnSunMeta, SunBase, SunMeta,
     },
-    utils::{log::warn_output, SunPointer},
+    utils::{log::warn_output, SunError, SunPointer},
 };
 
-/// Number 元数据
+/// Number 元数据，`Integer` 和 `Float` 共用
 #[derive(Clone, Debug)]
 pub struct SunNumber {
     meta: SunMeta,
@@ -39,8 +43,11 @@ impl SunNumber {
             ("sub", sub),
             ("mul", mul),
             ("div", div),
+            ("floordiv", floordiv),
             ("rem", rem),
             ("neg", neg),
+            ("pow", pow),
+            ("fac", fac),
             ("eq", eq),
             ("noteq", noteq),
             ("le", le),
@@ -50,34 +57,112 @@ impl SunNumber {
         );
         SunNumber { meta }
     }
+
+    /// 向下取整的除法
+    fn floordiv() -> Function {
+        let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
+            let arg_0 = args[0].get();
+            let arg_1 = args[1].get();
+            let value = match Operands::new(&arg_0, &arg_1) {
+                Some(Operands::Integer(n1, n2)) => {
+                    check_zero(n2)?;
+                    let value = n1.checked_div(n2).ok_or_else(|| overflow(n1, "~/", n2))?;
+                    // 商为负数且不能整除时向下取整
+                    if n1 % n2 != 0 && (n1 < 0) != (n2 < 0) {
+                        SunValue::from(value - 1)
+                    } else {
+                        SunValue::from(value)
+                    }
+                }
+                Some(Operands::Float(n1, n2)) => SunValue::from((n1 / n2).floor()),
+                None => return Err(mismatch("~/", &arg_0, &arg_1)),
+            };
+            Ok(vec![SunPointer::new(value)])
+        };
+        Function::from(f as RustFunction)
+    }
+}
+
+impl Default for SunNumber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 二元运算的操作数，两个 `Integer` 按整数运算，否则提升为 `Float` 运算
+pub enum Operands {
+    Integer(i64, i64),
+    Float(f64, f64),
+}
+
+impl Operands {
+    /// 获取两个数字的操作数，存在非数字时返回 `None`
+    pub fn new(n1: &SunValue, n2: &SunValue) -> Option<Self> {
+        match (n1, n2) {
+            (SunValue::Integer(n1), SunValue::Integer(n2)) => Some(Operands::Integer(*n1, *n2)),
+            _ => Some(Operands::Float(to_float(n1)?, to_float(n2)?)),
+        }
+    }
+}
+
+/// 将数字转换为浮点数
+pub fn to_float(value: &SunValue) -> Option<f64> {
+    match value {
+        SunValue::Integer(n) => Some(*n as f64),
+        SunValue::Float(n) => Some(*n),
+        _ => None,
+    }
+}
+
+/// 整数运算溢出的错误
+pub fn overflow(n1: i64, op: &str, n2: i64) -> SunError {
+    SunError::ArithmeticError(format!(
+        "integer overflow when calculating `{n1} {op} {n2}`"
+    ))
+}
+
+/// 检查整数除数是否为零
+fn check_zero(n: i64) -> Result<(), SunError> {
+    if n == 0 {
+        let e = SunError::ArithmeticError("integer divided by zero".to_string());
+        return Err(e);
+    }
+    Ok(())
 }
 
 impl AddAble for SunNumber {
     fn add() -> Function {
-        double_op!(+)
+        double_op!(+, checked_add)
     }
 }
 
 impl SubAble for SunNumber {
     fn sub() -> Function {
-        double_op!(-)
+        double_op!(-, checked_sub)
     }
 }
 
 impl MulAble for SunNumber {
     fn mul() -> Function {
-        double_op!(*)
+        double_op!(*, checked_mul)
     }
 }
 
 impl DivAble for SunNumber {
+    /// 两个 `Integer` 相除时向零取整
     fn div() -> Function {
         let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
             let arg_0 = args[0].get();
             let arg_1 = args[1].get();
-            let mut self_value = args[0].borrow_mut();
-            let res = match (arg_0, arg_1) {
-                (SunValue::Number(n1), SunValue::Number(n2)) => {
+            let value = match Operands::new(&arg_0, &arg_1) {
+                Some(Operands::Integer(n1, n2)) => {
+                    check_zero(n2)?;
+                    let value = n1.checked_div(n2).ok_or_else(|| overflow(n1, "/", n2))?;
+                    SunValue::from(value)
+                }
+                Some(Operands::Float(n1, n2)) => {
                     let value = n1 / n2;
                     if value == f64::INFINITY {
                         warn_output(
@@ -85,12 +170,11 @@ impl DivAble for SunNumber {
                                 .color(Colors::YellowFg),
                         );
                     }
-                    *self_value = SunValue::from(value);
-                    vec![args[0].clone()]
+                    SunValue::from(value)
                 }
-                _ => Vec::new(),
+                None => return Err(mismatch("/", &arg_0, &arg_1)),
             };
-            res
+            Ok(vec![SunPointer::new(value)])
         };
         Function::from(f as RustFunction)
     }
@@ -99,21 +183,31 @@ impl DivAble for SunNumber {
 impl RemAble for SunNumber {
     fn rem() -> Function {
         let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
             let arg_0 = args[0].get();
             let arg_1 = args[1].get();
-            let mut self_value = args[0].borrow_mut();
-            let res = match (arg_0, arg_1) {
-                (SunValue::Number(n1), SunValue::Number(n2)) => {
-                    if n1.fract() != 0.0 || n2.fract() != 0.0 {
-                        warn_output(format!("parameter is not an integer so when take it's remainder there may be problems").color(Colors::YellowFg));
+            let value = match Operands::new(&arg_0, &arg_1) {
+                Some(Operands::Integer(n1, n2)) => {
+                    check_zero(n2)?;
+                    let value = n1.checked_rem(n2).ok_or_else(|| overflow(n1, "%", n2))?;
+                    // 与 `floordiv` 一致，余数与除数同号
+                    if value != 0 && (value < 0) != (n2 < 0) {
+                        SunValue::from(value + n2)
+                    } else {
+                        SunValue::from(value)
                     }
+                }
+                Some(Operands::Float(n1, n2)) => {
                     let value = n1 % n2;
-                    *self_value = SunValue::from(value);
-                    vec![args[0].clone()]
+                    if value != 0.0 && (value < 0.0) != (n2 < 0.0) {
+                        SunValue::from(value + n2)
+                    } else {
+                        SunValue::from(value)
+                    }
                 }
-                _ => Vec::new(),
+                None => return Err(mismatch("%", &arg_0, &arg_1)),
             };
-            res
+            Ok(vec![SunPointer::new(value)])
         };
         Function::from(f as RustFunction)
     }
@@ -121,26 +215,52 @@ impl RemAble for SunNumber {
 
 impl NegAble for SunNumber {
     fn neg() -> Function {
-        single_op!(-)
+        let f = |args: Vec<SunPointer>| {
+            check_args(&args, 1)?;
+            let value = match args[0].get() {
+                SunValue::Integer(n) => match n.checked_neg() {
+                    Some(value) => SunValue::from(value),
+                    None => {
+                        let e = SunError::ArithmeticError(format!(
+                            "integer overflow when calculating `-{n}`"
+                        ));
+                        return Err(e);
+                    }
+                },
+                SunValue::Float(n) => SunValue::from(-n),
+                other => {
+                    let e = SunError::TypeError(format!(
+                        "unsupported operand type for `-`: `{}`",
+                        other.get_name()
+                    ));
+                    return Err(e);
+                }
+            };
+            Ok(vec![SunPointer::new(value)])
+        };
+        Function::from(f as RustFunction)
     }
 }
 
-/*
 impl PowAble for SunNumber {
     fn pow() -> Function {
         let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
             let arg_0 = args[0].get();
             let arg_1 = args[1].get();
-            let mut self_value = args[0].borrow_mut();
-            let res = match (arg_0, arg_1) {
-                (SunValue::Number(n1), SunValue::Number(n2)) => {
-                    let value = n1.powf(n2);
-                    *self_value = SunValue::from(value);
-                    vec![args[0].clone()]
+            let value = match Operands::new(&arg_0, &arg_1) {
+                Some(Operands::Integer(n1, n2)) if n2 >= 0 => {
+                    let value = u32::try_from(n2)
+                        .ok()
+                        .and_then(|n2| n1.checked_pow(n2))
+                        .ok_or_else(|| overflow(n1, "^", n2))?;
+                    SunValue::from(value)
                 }
-                _ => Vec::new(),
+                Some(Operands::Integer(n1, n2)) => SunValue::from((n1 as f64).powf(n2 as f64)),
+                Some(Operands::Float(n1, n2)) => SunValue::from(n1.powf(n2)),
+                None => return Err(mismatch("^", &arg_0, &arg_1)),
             };
-            res
+            Ok(vec![SunPointer::new(value)])
         };
         Function::from(f as RustFunction)
     }
@@ -149,43 +269,34 @@ impl PowAble for SunNumber {
 impl FacAble for SunNumber {
     fn fac() -> Function {
         let f = |args: Vec<SunPointer>| {
-            let arg = args[0].get();
-            let mut self_value = args[0].borrow_mut();
-            let res = match arg {
-                SunValue::Number(n) => {
-                    if n.fract() != 0.0 {
-                        warn_output(format!("`{n}` is not an integer so when take it's factorial will ignore the fractional part").color(Colors::YellowFg));
-                    }
-                    let n = n as i64;
-                    if n >= 21 {
-                        let e = SunError::ParaError(format!("too big number `{n}` for factorial"));
-                        error_output(e);
-                    }
-                    let mut res = 1;
-                    if n != 0 {
-                        for i in 1..=n {
-                            res *= i;
-                        }
-                    }
-                    *self_value = SunValue::from(res as f64);
-                    vec![args[0].clone()]
+            check_args(&args, 1)?;
+            let value = match args[0].get() {
+                SunValue::Integer(n) if n >= 0 => (1..=n)
+                    .try_fold(1i64, |res, i| res.checked_mul(i))
+                    .ok_or_else(|| {
+                        SunError::ArithmeticError(format!("too big number `{n}` for factorial"))
+                    })?,
+                other => {
+                    let e = SunError::ParaError(format!(
+                        "factorial needs a non-negative `Integer` but got `{other}`"
+                    ));
+                    return Err(e);
                 }
-                _ => Vec::new(),
             };
-            res
+            Ok(vec![SunPointer::new(SunValue::from(value))])
         };
         Function::from(f as RustFunction)
     }
 }
-*/
 
 impl EqualAble for SunNumber {
+    /// 与其他类型的值不相等
     fn eq() -> Function {
-        compare_op!(==)
+        compare_op!(==, false)
     }
 
     fn noteq() -> Function {
-        compare_op!(!=)
+        compare_op!(!=, true)
     }
 }
 
@@ -207,63 +318,53 @@ impl CompareAble for SunNumber {
     }
 }
 
-/// 批量处理 Number 的二元操作符
+/// 批量处理 Number 的二元操作符，`Integer` 之间的运算使用 `$checked` 检查溢出
 #[macro_export]
 macro_rules! double_op {
-    ($op:tt) => {{
+    ($op:tt, $checked:ident) => {{
+        use sun_core::meta::meta_methods::{check_args, mismatch};
+        use $crate::value::sun_number::{overflow, Operands};
         let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
             let arg_0 = args[0].get();
             let arg_1 = args[1].get();
-            let mut self_value = args[0].borrow_mut();
-            let res = match (arg_0, arg_1) {
-                (SunValue::Number(n1), SunValue::Number(n2)) => {
-                    let value = n1 $op n2;
-                    *self_value = SunValue::from(value);
-                    vec![args[0].clone()]
-                }
-                _ => Vec::new(),
-            };
-            res
-        };
-        Function::from(f as RustFunction)
-    }};
-}
-
-/// 批量处理 Number 的一元操作符
-#[macro_export]
-macro_rules! single_op {
-    ($op:tt) => {{
-        let f = |args: Vec<SunPointer>| {
-            let arg = args[0].get();
-            let mut self_value = args[0].borrow_mut();
-            let res = match arg {
-                SunValue::Number(n) => {
-                    *self_value = SunValue::from($op n);
-                    vec![args[0].clone()]
-                }
-                _ => Vec::new(),
+            let value = match Operands::new(&arg_0, &arg_1) {
+                Some(Operands::Integer(n1, n2)) => match n1.$checked(n2) {
+                    Some(value) => SunValue::from(value),
+                    None => return Err(overflow(n1, stringify!($op), n2)),
+                },
+                Some(Operands::Float(n1, n2)) => SunValue::from(n1 $op n2),
+                None => return Err(mismatch(stringify!($op), &arg_0, &arg_1)),
             };
-            res
+            Ok(vec![SunPointer::new(value)])
         };
         Function::from(f as RustFunction)
     }};
 }
 
-/// 批量处理 Number 的比较操作符
+/// 批量处理 Number 的比较操作符，`$mismatch`: 与非数字比较时的结果，省略时抛出 `TypeError`
 #[macro_export]
 macro_rules! compare_op {
-    ($op:tt) => {{
+    ($op:tt) => {
+        $crate::compare_op!(@ $op, None)
+    };
+    ($op:tt, $mismatch:expr) => {
+        $crate::compare_op!(@ $op, Some($mismatch))
+    };
+    (@ $op:tt, $mismatch:expr) => {{
+        use sun_core::meta::meta_methods::{check_args, mismatch};
+        use $crate::value::sun_number::Operands;
         let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
             let arg_0 = args[0].get();
             let arg_1 = args[1].get();
-            let res = match (arg_0, arg_1) {
-                (SunValue::Number(n1), SunValue::Number(n2)) => {
-                    let value = n1 $op n2;
-                    vec![SunPointer::new(SunValue::from(value))]
-                }
-                _ => Vec::new(),
+            let value = match (Operands::new(&arg_0, &arg_1), $mismatch) {
+                (Some(Operands::Integer(n1, n2)), _) => n1 $op n2,
+                (Some(Operands::Float(n1, n2)), _) => n1 $op n2,
+                (None, Some(value)) => value,
+                (None, None) => return Err(mismatch(stringify!($op), &arg_0, &arg_1)),
             };
-            res
+            Ok(vec![SunPointer::new(SunValue::from(value))])
         };
         Function::from(f as RustFunction)
     }};
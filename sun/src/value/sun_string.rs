@@ -0,0 +1,84 @@
+use sun_core::{
+    add_meta_methods,
+    container::{Function, RustFunction, SunValue},
+    meta::{
+        meta_methods::{
+            check_args, mismatch,
+            op::{AddAble, EqualAble},
+        },
+        OwnSunMeta, SunBase, SunMeta,
+    },
+    utils::SunPointer,
+};
+
+/// String 元数据
+#[derive(Clone, Debug)]
+pub struct SunString {
+    meta: SunMeta,
+}
+
+impl SunString {
+    /// 创建新的 String 元数据
+    pub fn new() -> SunString {
+        let mut meta = SunMeta::new("String", SunBase::Object);
+        add_meta_methods!(meta, SunString, ("add", add), ("eq", eq), ("noteq", noteq));
+        SunString { meta }
+    }
+}
+
+impl Default for SunString {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OwnSunMeta for SunString {
+    fn get_meta(&self) -> &SunMeta {
+        &self.meta
+    }
+
+    fn get_meta_mut(&mut self) -> &mut SunMeta {
+        &mut self.meta
+    }
+}
+
+impl AddAble for SunString {
+    /// 拼接字符串，其他类型的值转换为字符串后拼接
+    fn add() -> Function {
+        let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
+            match (args[0].get(), args[1].get()) {
+                (SunValue::String(mut s1), SunValue::String(s2)) => {
+                    s1.extend(s2);
+                    Ok(vec![SunPointer::new(SunValue::String(s1))])
+                }
+                (SunValue::String(mut s1), other) => {
+                    s1.extend(other.to_string().into_bytes());
+                    Ok(vec![SunPointer::new(SunValue::String(s1))])
+                }
+                (other, arg) => Err(mismatch("+", &other, &arg)),
+            }
+        };
+        Function::from(f as RustFunction)
+    }
+}
+
+impl EqualAble for SunString {
+    fn eq() -> Function {
+        let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
+            let value = args[0].get() == args[1].get();
+            Ok(vec![SunPointer::new(SunValue::from(value))])
+        };
+        Function::from(f as RustFunction)
+    }
+
+    fn noteq() -> Function {
+        let f = |args: Vec<SunPointer>| {
+            check_args(&args, 2)?;
+            let value = args[0].get() != args[1].get();
+            Ok(vec![SunPointer::new(SunValue::from(value))])
+        };
+        Function::from(f as RustFunction)
+    }
+}
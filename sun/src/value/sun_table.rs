@@ -1,12 +1,12 @@
 use colorized::*;
 use sun_core::{
     add_meta_methods, add_methods,
-    container::{Function, RustFunction, SunValue},
-    meta::{meta_methods::container::IndexAble, OwnSunMeta, SunBase, SunMeta},
-    utils::{
-        log::{error_output, warn_output},
-        SunError, SunPointer,
+    container::{Function, RustFunction, SunValue, TableKey},
+    meta::{
+        meta_methods::{check_args, container::IndexAble},
+        OwnSunMeta, SunBase, SunMeta,
     },
+    utils::{log::warn_output, SunError, SunPointer},
 };
 
 /// `Table` 类型的元数据
@@ -41,27 +41,34 @@ impl SunTable {
             ("alen", alen),
             ("dlen", dlen),
             ("len", len),
+            ("pairs", pairs),
             ("clone", clone)
         );
         SunTable { meta }
     }
 }
 
+impl Default for SunTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// 重写自身的深拷贝
 fn clone() -> Function {
     let f = |args: Vec<SunPointer>| {
-        if args.len() < 1 {
+        if args.is_empty() {
             {
-                let e = SunError::ParaError(format!("the number of parameters is too few"));
-                error_output(e);
+                let e = SunError::ParaError("the number of parameters is too few".to_string());
+                return Err(e);
             }
         }
         let p = args[0].deep_copy();
         let p = p.borrow();
         if let SunValue::Table(t) = &*p {
-            vec![SunPointer::new(SunValue::from(t.deep_copy()))]
+            Ok(vec![SunPointer::new(SunValue::from(t.deep_copy()))])
         } else {
-            vec![]
+            Ok(vec![])
         }
     };
     Function::from(f as RustFunction)
@@ -72,8 +79,8 @@ fn remove() -> Function {
     let f = |mut args: Vec<SunPointer>| {
         if args.len() <= 1 {
             {
-                let e = SunError::ParaError(format!("the number of parameters is too few"));
-                error_output(e);
+                let e = SunError::ParaError("the number of parameters is too few".to_string());
+                return Err(e);
             }
         }
         let p = args.remove(0);
@@ -81,42 +88,22 @@ fn remove() -> Function {
         let mut t = p.borrow_mut();
         if let SunValue::Table(t) = &mut *t {
             for arg in args {
-                match arg.get() {
-                    key @ SunValue::String(_) => match t.remove_by_key((&key).to_string().as_str())
-                    {
-                        Some(p) => res.push(p),
-                        None => {
-                            warn_output(
-                                format!("failed to find target value by key `{key}` so the table will not be changed")
-                                    .color(Colors::YellowFg),
-                            );
-                        }
-                    },
-                    SunValue::Number(idx) => {
-                        if idx.fract() != 0.0 {
-                            warn_output(
-                                format!("index is not an integer so there may be problems")
-                                    .color(Colors::YellowFg),
-                            );
-                        }
-                        match t.remove_by_idx(idx as usize) {
-                            Some(p) => res.push(p),
-                            None => {
-                                warn_output(
-                                    format!("failed to find target value by index `{idx}` so the table will not be changed")
-                                        .color(Colors::YellowFg),
-                                );
-                            }
-                        }
-                    }
-                    other => {
-                        let e = SunError::ParaError(format!("invalid key or index `{other}`"));
-                        error_output(e);
+                let Some(key) = TableKey::new(&arg) else {
+                    let e = SunError::ParaError(format!("invalid key or index `{}`", arg.get()));
+                    return Err(e);
+                };
+                match t.remove(&key) {
+                    Some(p) => res.push(p),
+                    None => {
+                        warn_output(
+                            format!("failed to find target value by key `{key}` so the table will not be changed")
+                                .color(Colors::YellowFg),
+                        );
                     }
                 }
             }
         }
-        res
+        Ok(res)
     };
     Function::from(f as RustFunction)
 }
@@ -126,8 +113,8 @@ fn push() -> Function {
     let f = |mut args: Vec<SunPointer>| {
         if args.len() <= 1 {
             {
-                let e = SunError::ParaError(format!("the number of parameters is too few"));
-                error_output(e);
+                let e = SunError::ParaError("the number of parameters is too few".to_string());
+                return Err(e);
             }
         }
         let p = args.remove(0);
@@ -138,7 +125,7 @@ fn push() -> Function {
                 t.append(value)
             }
         }
-        vec![]
+        Ok(vec![])
     };
     Function::from(f as RustFunction)
 }
@@ -148,40 +135,42 @@ fn insert() -> Function {
     let f = |mut args: Vec<SunPointer>| {
         if args.len() <= 2 {
             {
-                let e = SunError::ParaError(format!("the number of parameters is too few"));
-                error_output(e);
+                let e = SunError::ParaError("the number of parameters is too few".to_string());
+                return Err(e);
             }
         }
         let p = args.remove(0);
         let mut t = p.borrow_mut();
         if let SunValue::Table(t) = &mut *t {
             match (args[0].get(), args[1].get()) {
-                (key @ SunValue::String(_), value) => {
-                    if let Some(_) = t.get_by_key((&key).to_string().as_str()) {
+                (SunValue::Integer(index), value) => {
+                    // 整数在数组中插入
+                    match usize::try_from(index) {
+                        Ok(index) if index <= t.array().len() => t.insert(index, value),
+                        _ => {
+                            let e = SunError::ParaError(format!(
+                                "index `{index}` is out of range for insert"
+                            ));
+                            return Err(e);
+                        }
+                    }
+                }
+                (other, _) => {
+                    let Some(key) = TableKey::new(&args[0]) else {
+                        let e = SunError::KeyError(format!("invalid key `{other}`"));
+                        return Err(e);
+                    };
+                    if t.get(&key).is_some() {
                         warn_output(
                             format!("key `{key}` already exists so the value will be changed")
                                 .color(Colors::YellowFg),
                         );
                     }
-                    t.append_kv((&key).into(), value)
-                }
-                (SunValue::Number(index), value) => {
-                    if index < 0.0 {
-                        let e = SunError::ParaError(format!("negative can't be index"));
-                        error_output(e);
-                    }
-                    if index.fract() != 0.0 {
-                        warn_output(format!("parameter is not an integer so it's decimal part will be truncated as an index").color(Colors::YellowFg));
-                    }
-                    t.insert(index as usize, value)
-                }
-                (other, _) => {
-                    let e = SunError::KeyError(format!("invalid key `{other}`"));
-                    error_output(e);
+                    t.set(key, args[1].copy_value())
                 }
             }
         }
-        vec![]
+        Ok(vec![])
     };
     Function::from(f as RustFunction)
 }
@@ -191,8 +180,8 @@ fn extend() -> Function {
     let f = |mut args: Vec<SunPointer>| {
         if args.len() <= 1 {
             {
-                let e = SunError::ParaError(format!("the number of parameters is too few"));
-                error_output(e);
+                let e = SunError::ParaError("the number of parameters is too few".to_string());
+                return Err(e);
             }
         }
         let p = args.remove(0);
@@ -205,11 +194,11 @@ fn extend() -> Function {
                 }
                 other => {
                     let e = SunError::ParaError(format!("expect `table` but got `{other}`"));
-                    error_output(e);
+                    return Err(e);
                 }
             }
         }
-        vec![]
+        Ok(vec![])
     };
     Function::from(f as RustFunction)
 }
@@ -219,8 +208,8 @@ fn aextend() -> Function {
     let f = |mut args: Vec<SunPointer>| {
         if args.len() <= 1 {
             {
-                let e = SunError::ParaError(format!("the number of parameters is too few"));
-                error_output(e);
+                let e = SunError::ParaError("the number of parameters is too few".to_string());
+                return Err(e);
             }
         }
         let p = args.remove(0);
@@ -230,11 +219,11 @@ fn aextend() -> Function {
                 SunValue::Table(t2) => t.extend_array(t2.clone()),
                 other => {
                     let e = SunError::ParaError(format!("expect `table` but got `{other}`"));
-                    error_output(e);
+                    return Err(e);
                 }
             }
         }
-        vec![]
+        Ok(vec![])
     };
     Function::from(f as RustFunction)
 }
@@ -244,8 +233,8 @@ fn dextend() -> Function {
     let f = |mut args: Vec<SunPointer>| {
         if args.len() <= 1 {
             {
-                let e = SunError::ParaError(format!("the number of parameters is too few"));
-                error_output(e);
+                let e = SunError::ParaError("the number of parameters is too few".to_string());
+                return Err(e);
             }
         }
         let p = args.remove(0);
@@ -257,11 +246,11 @@ fn dextend() -> Function {
                 }
                 other => {
                     let e = SunError::ParaError(format!("expect `table` but got `{other}`"));
-                    error_output(e);
+                    return Err(e);
                 }
             }
         }
-        vec![]
+        Ok(vec![])
     };
     Function::from(f as RustFunction)
 }
@@ -269,18 +258,18 @@ fn dextend() -> Function {
 /// 获取 `Table` 数组部分长度
 fn alen() -> Function {
     let f = |args: Vec<SunPointer>| {
-        if args.len() < 1 {
+        if args.is_empty() {
             {
-                let e = SunError::ParaError(format!("the number of parameters is too few"));
-                error_output(e);
+                let e = SunError::ParaError("the number of parameters is too few".to_string());
+                return Err(e);
             }
         }
         let p = args[0].get();
         match p {
-            SunValue::Table(t) => vec![t.alen()],
+            SunValue::Table(t) => Ok(vec![t.alen()]),
             other => {
                 let e = SunError::ParaError(format!("expect `table` but got `{other}`"));
-                error_output(e);
+                Err(e)
             }
         }
     };
@@ -290,18 +279,18 @@ fn alen() -> Function {
 /// 获取 `Table` 字典部分长度
 fn dlen() -> Function {
     let f = |args: Vec<SunPointer>| {
-        if args.len() < 1 {
+        if args.is_empty() {
             {
-                let e = SunError::ParaError(format!("the number of parameters is too few"));
-                error_output(e);
+                let e = SunError::ParaError("the number of parameters is too few".to_string());
+                return Err(e);
             }
         }
         let p = args[0].get();
         match p {
-            SunValue::Table(t) => vec![t.dlen()],
+            SunValue::Table(t) => Ok(vec![t.dlen()]),
             other => {
                 let e = SunError::ParaError(format!("expect `table` but got `{other}`"));
-                error_output(e);
+                Err(e)
             }
         }
     };
@@ -311,18 +300,38 @@ fn dlen() -> Function {
 /// 获取 `Table` 总长度
 fn len() -> Function {
     let f = |args: Vec<SunPointer>| {
-        if args.len() < 1 {
+        if args.is_empty() {
             {
-                let e = SunError::ParaError(format!("the number of parameters is too few"));
-                error_output(e);
+                let e = SunError::ParaError("the number of parameters is too few".to_string());
+                return Err(e);
             }
         }
         let p = args[0].get();
         match p {
-            SunValue::Table(t) => vec![t.len()],
+            SunValue::Table(t) => Ok(vec![t.len()]),
             other => {
                 let e = SunError::ParaError(format!("expect `table` but got `{other}`"));
-                error_output(e);
+                Err(e)
+            }
+        }
+    };
+    Function::from(f as RustFunction)
+}
+
+/// 获取 `Table` 字典部分的键值对，用于 `for k, v in t.pairs()`
+fn pairs() -> Function {
+    let f = |args: Vec<SunPointer>| {
+        if args.is_empty() {
+            {
+                let e = SunError::ParaError("the number of parameters is too few".to_string());
+                return Err(e);
+            }
+        }
+        match &*args[0].borrow() {
+            SunValue::Table(t) => Ok(vec![SunPointer::new(SunValue::from(t.pairs()))]),
+            other => {
+                let e = SunError::ParaError(format!("expect `table` but got `{other}`"));
+                Err(e)
             }
         }
     };
@@ -332,38 +341,34 @@ fn len() -> Function {
 impl IndexAble for SunTable {
     fn index() -> Function {
         let f = |args: Vec<SunPointer>| {
-            let arg_0 = args[0].get();
-            let arg_1 = args[1].get();
-            let res = match (arg_0, arg_1) {
-                (SunValue::Table(t), SunValue::Number(idx)) => {
-                    if idx.fract() != 0.0 {
-                        warn_output(
-                            format!("index is not an integer so there may be problems")
-                                .color(Colors::YellowFg),
-                        );
-                    }
-                    if let Some(res) = t.get_by_idx(idx as usize) {
-                        vec![res]
-                    } else {
+            check_args(&args, 2)?;
+            let table = args[0].borrow();
+            let t = match &*table {
+                SunValue::Table(t) => t,
+                _ => {
+                    let e = SunError::ParaError("invalid parameters for get value".to_string());
+                    return Err(e);
+                }
+            };
+            let res = match TableKey::new(&args[1]) {
+                Some(key) => match t.get(&key) {
+                    Some(res) => res,
+                    None if matches!(key, TableKey::Integer(_)) => {
                         let e =
-                            SunError::IndexError(format!("failed to get value by index `{idx}`"));
-                        error_output(e);
+                            SunError::IndexError(format!("failed to get value by index `{key}`"));
+                        return Err(e);
                     }
-                }
-                (SunValue::Table(t), key @ SunValue::String(_)) => {
-                    if let Some(res) = t.get_by_key((&key).to_string().as_str()) {
-                        vec![res]
-                    } else {
+                    None => {
                         let e = SunError::KeyError(format!("failed to get value by key `{key}`"));
-                        error_output(e);
+                        return Err(e);
                     }
-                }
-                _ => {
-                    let e = SunError::ParaError(format!("invalid parameters for get value"));
-                    error_output(e);
+                },
+                None => {
+                    let e = SunError::KeyError(format!("`{}` can't be a key", args[1].get()));
+                    return Err(e);
                 }
             };
-            res
+            Ok(vec![res])
         };
         Function::from(f as RustFunction)
     }
@@ -0,0 +1,239 @@
+use crate::vm::command::Command;
+use std::cell::RefCell;
+use std::rc::Rc;
+use sun_core::{
+    container::{SunValue, TableIter, Upvalue},
+    utils::{Span, SunPointer},
+};
+
+/// for 循环使用的迭代器
+#[derive(Debug)]
+pub enum SunIter {
+    /// 遍历 `Table` 的数组部分
+    Table(TableIter),
+    /// 遍历 `[start, end)` 区间中步长为 1 的整数
+    Range(i64, i64),
+}
+
+impl Iterator for SunIter {
+    type Item = SunPointer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            SunIter::Table(iter) => iter.next(),
+            SunIter::Range(start, end) => {
+                if *start < *end {
+                    let value = *start;
+                    *start += 1;
+                    Some(SunPointer::new(SunValue::from(value)))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// try 语句块的错误处理信息
+#[derive(Debug)]
+pub struct Handler {
+    /// `Try` 指令的位置
+    start: usize,
+    /// catch 语句块的位置
+    catch: usize,
+    /// 进入 try 语句块时栈的长度
+    base: usize,
+    /// 进入 try 语句块时迭代器栈的长度
+    iters: usize,
+    /// 进入 try 语句块时有值语句块的层数
+    marks: usize,
+}
+
+impl Handler {
+    /// 位置 `pc` 是否在 try 语句块中
+    fn contains(&self, pc: usize) -> bool {
+        self.start < pc && pc < self.catch
+    }
+}
+
+/// 调用帧，按槽位保存局部变量的存储单元和闭包捕获的变量
+#[derive(Debug)]
+pub struct Frame {
+    /// 所属函数的函数名，顶层语段为 `main`
+    name: String,
+    /// 执行的指令序列
+    code: Rc<Vec<(Command, Span)>>,
+    /// 当前指令的位置，从 1 开始
+    pc: usize,
+    /// 进入调用帧时栈的长度，返回时之上的栈顶值作为返回值
+    base: usize,
+    /// 调用者所在的模块，返回时切换回该模块
+    caller: Option<Rc<str>>,
+    /// 局部变量的存储单元
+    locals: Vec<Upvalue>,
+    /// 捕获的变量
+    upvalues: Vec<Upvalue>,
+    /// 当前所在的 try 语句块，由外向内
+    handlers: Vec<Handler>,
+    /// 当前所在的 for 循环的迭代器，由外向内
+    iters: Vec<SunIter>,
+    /// 当前所在的有值语句块开始时栈的长度，由外向内
+    marks: Vec<usize>,
+}
+
+impl Frame {
+    /// 创建执行 `code` 的指定大小的调用帧，局部变量初始化为 `Nil`
+    pub fn new(
+        name: &str,
+        code: Rc<Vec<(Command, Span)>>,
+        size: usize,
+        upvalues: Vec<Upvalue>,
+    ) -> Self {
+        Frame {
+            name: name.to_string(),
+            code,
+            pc: 1,
+            base: 0,
+            caller: None,
+            locals: (0..size).map(|_| Self::new_cell()).collect(),
+            upvalues,
+            handlers: Vec::new(),
+            iters: Vec::new(),
+            marks: Vec::new(),
+        }
+    }
+
+    /// 所属函数的函数名
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 记录返回时需要恢复的状态 `base`: 调用时栈的长度 `caller`: 调用者所在的模块
+    pub fn returns_to(mut self, base: usize, caller: Option<Rc<str>>) -> Self {
+        self.base = base;
+        self.caller = caller;
+        self
+    }
+
+    /// 执行的指令序列
+    pub fn code(&self) -> Rc<Vec<(Command, Span)>> {
+        Rc::clone(&self.code)
+    }
+
+    /// 当前指令的位置
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// 跳转到位置 `pc` 的指令
+    pub fn set_pc(&mut self, pc: usize) {
+        self.pc = pc;
+    }
+
+    /// 进入调用帧时栈的长度
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// 调用者所在的模块
+    pub fn caller(&self) -> Option<Rc<str>> {
+        self.caller.clone()
+    }
+
+    /// 创建值为 `Nil` 的存储单元
+    fn new_cell() -> Upvalue {
+        Rc::new(RefCell::new(SunPointer::new(SunValue::Nil)))
+    }
+
+    /// 为局部变量分配新的存储单元，已被闭包捕获的旧存储单元不受影响
+    pub fn new_local(&mut self, slot: usize) {
+        self.locals[slot] = Self::new_cell();
+    }
+
+    /// 读取局部变量
+    pub fn load_local(&self, slot: usize) -> SunPointer {
+        self.locals[slot].borrow().clone()
+    }
+
+    /// 写入局部变量
+    pub fn store_local(&mut self, slot: usize, value: SunPointer) {
+        *self.locals[slot].borrow_mut() = value;
+    }
+
+    /// 获取局部变量的存储单元
+    pub fn capture_local(&self, slot: usize) -> Upvalue {
+        Rc::clone(&self.locals[slot])
+    }
+
+    /// 读取捕获的变量
+    pub fn load_upvalue(&self, index: usize) -> SunPointer {
+        self.upvalues[index].borrow().clone()
+    }
+
+    /// 写入捕获的变量
+    pub fn store_upvalue(&mut self, index: usize, value: SunPointer) {
+        *self.upvalues[index].borrow_mut() = value;
+    }
+
+    /// 获取捕获变量的存储单元
+    pub fn capture_upvalue(&self, index: usize) -> Upvalue {
+        Rc::clone(&self.upvalues[index])
+    }
+
+    /// 进入 try 语句块，通过 `break` 或 `continue` 离开的 try 语句块一并移除
+    pub fn push_handler(&mut self, start: usize, catch: usize, base: usize) {
+        self.handlers.retain(|handler| handler.contains(start));
+        let iters = self.iters.len();
+        let marks = self.marks.len();
+        self.handlers.push(Handler {
+            start,
+            catch,
+            base,
+            iters,
+            marks,
+        });
+    }
+
+    /// 离开 try 语句块
+    pub fn pop_handler(&mut self) {
+        self.handlers.pop();
+    }
+
+    /// 查找包含位置 `pc` 的最内层 try 语句块，返回 catch 语句块的位置和栈的长度，
+    /// 同时丢弃 try 语句块中未结束的迭代器和有值语句块
+    pub fn catch(&mut self, pc: usize) -> Option<(usize, usize)> {
+        while let Some(handler) = self.handlers.pop() {
+            if handler.contains(pc) {
+                self.iters.truncate(handler.iters);
+                self.marks.truncate(handler.marks);
+                return Some((handler.catch, handler.base));
+            }
+        }
+        None
+    }
+
+    /// 进入 for 循环
+    pub fn push_iter(&mut self, iter: SunIter) {
+        self.iters.push(iter);
+    }
+
+    /// 获取当前 for 循环的下一个值
+    pub fn next_iter(&mut self) -> Option<SunPointer> {
+        self.iters.last_mut().and_then(|iter| iter.next())
+    }
+
+    /// 离开 for 循环
+    pub fn pop_iter(&mut self) {
+        self.iters.pop();
+    }
+
+    /// 进入有值的语句块 `base`: 栈的长度
+    pub fn push_mark(&mut self, base: usize) {
+        self.marks.push(base);
+    }
+
+    /// 离开有值的语句块，返回进入时栈的长度
+    pub fn pop_mark(&mut self) -> Option<usize> {
+        self.marks.pop()
+    }
+}
@@ -1,26 +1,67 @@
-use crate::{parser::parser::ParseProto, prelude::prelude, vm::command::Command};
+use crate::{
+    parser::parser::ParseProto,
+    prelude::prelude,
+    vm::{
+        command::Command,
+        frame::{Frame, SunIter},
+        module::{self, Module},
+    },
+};
+use std::any::TypeId;
 use std::collections::HashMap;
-use std::io::Read;
+use std::env;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::PathBuf;
+use std::rc::Rc;
 use sun_core::{
-    container::{Function, SunValue, Table},
-    meta::{SunBase, SunMeta},
+    container::{
+        Capture, Class, Closure, Function, SunValue, Table, TableIter, TableKey, Upvalue,
+        UserFunction,
+    },
+    meta::{OwnSunMeta, SunBase, SunMeta},
     utils::{
-        log::{debug_output, error_output, log_output, warn_output},
+        log::{debug_output, log_output, warn_output},
         machine::IsMachine,
-        SunError, SunPointer,
+        Span, SunError, SunPointer,
     },
+    ExportLib, ImportAble, SunClass,
 };
+use sun_lib::Math;
+
+/// 默认的调用栈最大深度，超过时抛出 `RunError`，避免无限递归耗尽内存
+const MAX_DEPTH: usize = 10_000;
 
 /// Sun 虚拟机的结构体
-pub struct VirtualMachine<'a> {
+pub struct VirtualMachine {
     /// 调用栈
     stack: Vec<SunPointer>,
-    /// 全局变量表
+    /// 全局变量表，执行模块中的代码时为该模块的全局变量表
     value_map: HashMap<String, SunPointer>,
-    /// 临时变量表
-    // temp_map: HashMap<String, SunPointer>,
+    /// 预导入的全局变量，作为模块全局变量表的初始值
+    builtins: HashMap<String, SunPointer>,
+    /// 当前全局变量表所属的模块，`None` 为主程序
+    module: Option<Rc<str>>,
+    /// 执行模块中的代码时保存的主程序的全局变量表
+    main_globals: HashMap<String, SunPointer>,
+    /// 已导入的模块，键为模块文件的绝对路径或原生库名
+    modules: HashMap<Rc<str>, Module>,
+    /// 正在导入的模块，由外向内
+    loading: Vec<Rc<str>>,
+    /// 模块的查找目录，`None` 时使用环境变量 `SUN_PATH`
+    search_paths: Option<Vec<PathBuf>>,
+    /// 已注册的原生库，键为导入时使用的库名
+    libs: HashMap<String, Rc<dyn Fn() -> ExportLib>>,
+    /// 由 `register_lib` 注册的原生库，同一个类型以不同的名字注册时共用同一个库
+    lib_types: HashMap<TypeId, Rc<dyn Fn() -> ExportLib>>,
+    /// 调用帧栈，每个调用帧按槽位保存局部变量
+    frames: Vec<Frame>,
+    /// 调用栈的最大深度
+    max_depth: usize,
+    /// 未被捕获的错误经过的位置和函数名，由内向外
+    trace: Vec<(Span, String)>,
     /// meta表
-    meta_map: HashMap<&'a str, SunMeta>,
+    meta_map: HashMap<String, SunMeta>,
     /// 函数表
     // function_map: HashMap<String, Vec<Command>>,
     /// debug 模式标志
@@ -31,13 +72,23 @@ pub struct VirtualMachine<'a> {
     check_stack: bool,
 }
 
-impl<'a> VirtualMachine<'a> {
+impl VirtualMachine {
     /// 创建新的虚拟机
     pub fn new(is_debug: bool, check_stack: bool, check_global: bool) -> Self {
         let mut vm = VirtualMachine {
             stack: Vec::new(),
             value_map: HashMap::new(),
-            // temp_map: HashMap::new(),
+            builtins: HashMap::new(),
+            module: None,
+            main_globals: HashMap::new(),
+            modules: HashMap::new(),
+            loading: Vec::new(),
+            search_paths: None,
+            libs: HashMap::new(),
+            lib_types: HashMap::new(),
+            frames: Vec::new(),
+            max_depth: MAX_DEPTH,
+            trace: Vec::new(),
             meta_map: HashMap::new(),
             // function_map: HashMap::new(),
             is_debug,
@@ -46,285 +97,930 @@ impl<'a> VirtualMachine<'a> {
         };
         // 预导入的模块
         prelude(&mut vm.value_map, &mut vm.meta_map);
+        vm.builtins = vm.value_map.clone();
+        vm.register_lib::<Math>("math");
         vm
     }
 
+    /// 注册原生库 `T`，脚本中使用 `@"name"` 导入，`name`: 库名
+    pub fn register_lib<T: ImportAble + 'static>(&mut self, name: &str) {
+        let export_lib = self
+            .lib_types
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Rc::new(T::export_lib as fn() -> ExportLib))
+            .clone();
+        self.libs.insert(name.to_string(), export_lib);
+    }
+
+    /// 注册由闭包导出的原生库，闭包可以捕获宿主程序的状态，在第一次导入时调用
+    /// 每次注册的闭包都是独立的库
+    /// `name`: 库名 `export_lib`: 返回库导出内容的闭包
+    pub fn register_lib_with(&mut self, name: &str, export_lib: impl Fn() -> ExportLib + 'static) {
+        self.libs.insert(name.to_string(), Rc::new(export_lib));
+    }
+
+    /// 设置模块的查找目录，代替环境变量 `SUN_PATH` `paths`: 依次查找的目录
+    pub fn set_search_paths(&mut self, paths: Vec<PathBuf>) {
+        self.search_paths = Some(paths);
+    }
+
+    /// 设置调用栈的最大深度，脚本函数的调用不占用宿主程序的原生栈 `depth`: 最大深度
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
+    }
+
     /// 运行虚拟机 `proto`: 语法分析器
-    pub fn run<T: Read>(&mut self, proto: &ParseProto<T>) {
-        let mut pc = 1;
-        while pc <= proto.commands.len() {
-            let command = &proto.commands[pc - 1];
-            match &command {
-                Command::LoadValue(name) => {
-                    let value = self.value_map.get(name.as_str()).clone();
-                    if let Some(value) = value {
-                        self.stack.push(value.clone());
-                    } else {
-                        self.stack.push(SunPointer::new(SunValue::Nil));
+    pub fn run<T: Read>(&mut self, proto: &ParseProto<T>) -> Result<(), SunError> {
+        let base = self.stack.len();
+        self.trace.clear();
+        let code = Rc::clone(&proto.commands);
+        self.frames
+            .push(Frame::new("main", code, proto.frame_size, Vec::new()));
+        let res = self.execute();
+        self.frames.pop();
+        // 出错时丢弃未完成的运算留在栈上的值，并附上出错位置和调用栈
+        res.map_err(|e| {
+            self.stack.truncate(base);
+            SunError::TraceError(Box::new(e), std::mem::take(&mut self.trace))
+        })
+    }
+
+    /// 当前的调用帧
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    /// 执行栈顶的调用帧，直到该调用帧执行结束
+    /// 其中调用的脚本函数压入新的调用帧，在同一个循环中执行，不占用宿主程序的原生栈
+    fn execute(&mut self) -> Result<(), SunError> {
+        let entry = self.frames.len() - 1;
+        loop {
+            let index = self.frames.len() - 1;
+            let (code, mut pc) = (self.frame().code(), self.frame().pc());
+            // 执行到指令序列末尾等同于返回
+            let Some((command, span)) = code.get(pc - 1) else {
+                if index == entry {
+                    return Ok(());
+                }
+                self.return_from();
+                continue;
+            };
+            match self.step(command, span, &mut pc) {
+                // 调用脚本函数时栈顶是新的调用帧，调用者停在调用指令，函数返回后继续执行
+                Ok(true) => {
+                    self.debug(pc, command, span);
+                    let called = self.frames.len() > index + 1;
+                    self.frames[index].set_pc(if called { pc } else { pc + 1 });
+                }
+                Ok(false) => {
+                    self.debug(pc, command, span);
+                    if index == entry {
+                        return Ok(());
                     }
+                    self.return_from();
                 }
-                Command::LoadConst(value) => self.stack.push(SunPointer::new(value.clone())),
-                Command::LoadMethod(name) => {
-                    if name == "dot" {
-                        let self_value = self.stack.pop();
-                        match self_value.clone() {
-                            Some(p) => {
-                                let value = p.get();
-                                let meta_name = value.get_name();
-                                match self.stack.pop() {
-                                    Some(method_name) => match method_name.get() {
-                                        ref method_name @ SunValue::String(_) => {
-                                            let method_name: String = method_name.into();
-                                            let method =
-                                                self.get_method(meta_name, method_name.as_str());
-                                            self.stack.push(self_value.unwrap());
-                                            self.stack.push(SunPointer::new(SunValue::from(method)))
-                                        }
-                                        other => {
-                                            let e = SunError::ParaError(format!(
-                                                "expect attribute name but got `{other}`"
-                                            ));
-                                            error_output(e);
-                                        }
-                                    },
-                                    None => {
-                                        let e = SunError::RunError(format!(
-                                            "stack is empty so failed to find object"
-                                        ));
-                                        error_output(e);
-                                    }
-                                }
-                            }
-                            None => {
-                                let e = SunError::RunError(format!(
-                                    "stack is empty so failed to find object"
-                                ));
-                                error_output(e);
-                            }
-                        }
-                    } else {
-                        match self.stack.last() {
-                            Some(p) => {
-                                let value = p.get();
-                                let meta_name = value.get_name();
-                                let method = self.get_method(meta_name, name);
-                                self.stack.push(SunPointer::new(SunValue::from(method)));
-                            }
-                            None => {
-                                let e = SunError::RunError(format!(
-                                    "stack is empty so failed to find object"
-                                ));
-                                error_output(e);
-                            }
-                        }
+                Err(e) => self.unwind(e, entry)?,
+            }
+        }
+    }
+
+    /// 由内向外查找处理错误的 try 语句块，跳转到 catch 语句块，错误信息作为 catch 的变量；
+    /// 没有处理错误的 try 语句块时记录出错位置并离开调用帧，直到第 `entry` 层调用帧
+    fn unwind(&mut self, e: SunError, entry: usize) -> Result<(), SunError> {
+        loop {
+            let pc = self.frame().pc();
+            match self.frame().catch(pc) {
+                Some((catch, base)) => {
+                    self.stack.truncate(base);
+                    self.trace.clear();
+                    let error = SunValue::from(Table::from(e));
+                    self.stack.push(SunPointer::new(error));
+                    self.frame().set_pc(catch);
+                    return Ok(());
+                }
+                None => {
+                    let span = self.frame().code()[pc - 1].1.clone();
+                    let name = self.frame().name().to_string();
+                    self.trace.push((span, name));
+                    if self.frames.len() - 1 == entry {
+                        return Err(e);
                     }
+                    self.leave();
+                }
+            }
+        }
+    }
+
+    /// 从脚本函数返回，栈顶的值作为返回值，没有值时返回 `Nil`
+    fn return_from(&mut self) {
+        let base = self.frame().base();
+        self.leave();
+        let pc = self.frame().pc();
+        self.frame().set_pc(pc + 1);
+        let res = if self.stack.len() > base {
+            self.stack.pop().unwrap()
+        } else {
+            SunPointer::new(SunValue::Nil)
+        };
+        self.stack.truncate(base);
+        self.stack.push(res);
+    }
+
+    /// 离开栈顶的调用帧，切换回调用者所在的模块
+    fn leave(&mut self) {
+        if let Some(frame) = self.frames.pop() {
+            self.switch_module(frame.caller());
+        }
+    }
+
+    /// 执行单条指令 `command`: 指令 `span`: 指令的源码位置 `pc`: 程序计数器，返回 `false` 表示从当前函数返回
+    fn step(&mut self, command: &Command, span: &Span, pc: &mut usize) -> Result<bool, SunError> {
+        match command {
+            Command::LoadValue(name) => {
+                let value = self.value_map.get(name.as_str());
+                if let Some(value) = value {
+                    self.stack.push(value.clone());
+                } else {
+                    self.stack.push(SunPointer::new(SunValue::Nil));
                 }
-                Command::LoadMetamethod(meta_name, method_name) => {
-                    let method = self.get_method(meta_name, method_name);
+            }
+            Command::NewLocal(slot) => self.frame().new_local(*slot),
+            Command::LoadLocal(slot) => {
+                let value = self.frame().load_local(*slot);
+                self.stack.push(value);
+            }
+            Command::StoreLocal(slot) => match self.stack.pop() {
+                Some(value) => self.frame().store_local(*slot, value.copy_value()),
+                None => {
+                    let e = SunError::RunError(
+                        "stack is empty so failed to store local variable".to_string(),
+                    );
+                    return Err(e);
+                }
+            },
+            Command::LoadUpvalue(index) => {
+                let value = self.frame().load_upvalue(*index);
+                self.stack.push(value);
+            }
+            Command::StoreUpvalue(index) => match self.stack.pop() {
+                Some(value) => self.frame().store_upvalue(*index, value.copy_value()),
+                None => {
+                    let e = SunError::RunError(
+                        "stack is empty so failed to store captured variable".to_string(),
+                    );
+                    return Err(e);
+                }
+            },
+            Command::MakeClosure(captures) => match self.stack.pop().map(|p| p.get()) {
+                Some(SunValue::Function(Function::UserFunction(uf))) => {
+                    let frame = self.frames.last().unwrap();
+                    let upvalues = captures
+                        .iter()
+                        .map(|capture| match capture {
+                            Capture::Local(slot) => frame.capture_local(*slot),
+                            Capture::Upvalue(index) => frame.capture_upvalue(*index),
+                        })
+                        .collect();
+                    let closure = Closure::new(uf, upvalues);
+                    self.stack.push(SunPointer::new(SunValue::from(closure)));
+                }
+                _ => {
+                    let e = SunError::RunError(
+                        "stack is empty so failed to create closure".to_string(),
+                    );
+                    return Err(e);
+                }
+            },
+            Command::LoadConst(value) => self.stack.push(SunPointer::new(value.clone())),
+            Command::LoadMethod(name) => match self.stack.last() {
+                Some(p) => {
+                    let value = p.get();
+                    let meta_name = value.get_name();
+                    let method = self.get_method(meta_name, name)?;
                     self.stack.push(SunPointer::new(SunValue::from(method)));
                 }
-                Command::StoreGlobal(name) => {
-                    match self.stack.pop() {
-                        Some(value) => match value.get() {
-                            SunValue::Nil => {
-                                warn_output("Nil value will not be insert into global value map")
-                            }
-                            _ => {
-                                self.value_map.insert(name.to_string(), value);
-                            }
-                        },
-                        None => warn_output("Nil value will not be insert into global value map"),
-                    };
+                None => {
+                    let e =
+                        SunError::RunError("stack is empty so failed to find object".to_string());
+                    return Err(e);
                 }
-                Command::TestJump(jump) => match self.stack.pop() {
-                    Some(p) => {
-                        if let SunValue::Boolean(false) | SunValue::Nil = p.get() {
-                            pc += *jump;
-                        }
+            },
+            Command::LoadMetamethod(meta_name, method_name) => {
+                let method = self.get_method(meta_name, method_name)?;
+                self.stack.push(SunPointer::new(SunValue::from(method)));
+            }
+            Command::StoreGlobal(name) => {
+                match self.stack.pop() {
+                    Some(value) => {
+                        self.value_map.insert(name.to_string(), value.copy_value());
+                    }
+                    None => warn_output("Nil value will not be insert into global value map"),
+                };
+            }
+            Command::TestJump(jump) => match self.stack.pop() {
+                Some(p) => {
+                    if let SunValue::Boolean(false) | SunValue::Nil = p.get() {
+                        *pc += *jump;
+                    }
+                }
+                None => {
+                    let e = SunError::RunError(
+                        "stack is empty so failed to find get condition".to_string(),
+                    );
+                    return Err(e);
+                }
+            },
+            Command::Jump(jump) => {
+                *pc += jump;
+            }
+            Command::Back(jump) => {
+                *pc -= jump;
+            }
+            Command::Return => return Ok(false),
+            Command::Switch(table) => {
+                let value = self.pop_value("value to match")?;
+                let jump = TableKey::new(&value).and_then(|key| table.get(&key));
+                if let Some(jump) = jump {
+                    *pc += jump;
+                }
+            }
+            Command::MatchTable(keys, jump) => {
+                let value = self.pop_value("value to match")?;
+                let values = match &*value.borrow() {
+                    SunValue::Table(t) => keys
+                        .iter()
+                        .map(|key| t.get_by_key(key))
+                        .collect::<Option<Vec<_>>>(),
+                    _ => None,
+                };
+                match values {
+                    Some(values) => self.stack.extend(values.into_iter().rev()),
+                    None => *pc += *jump,
+                }
+            }
+            Command::MatchRange(start, end, jump) => {
+                let value = self.pop_value("value to match")?;
+                let number = |value: &SunValue| match value {
+                    SunValue::Integer(i) => Some(*i as f64),
+                    SunValue::Float(f) => Some(*f),
+                    _ => None,
+                };
+                let matched = match (number(start), number(end), number(&value.borrow())) {
+                    (Some(start), Some(end), Some(value)) => start <= value && value < end,
+                    _ => false,
+                };
+                if !matched {
+                    *pc += *jump;
+                }
+            }
+            Command::Try(jump) => {
+                let base = self.stack.len();
+                let start = *pc;
+                self.frame().push_handler(start, start + jump + 1, base);
+            }
+            Command::EndTry => self.frame().pop_handler(),
+            Command::BlockStart => {
+                let base = self.stack.len();
+                self.frame().push_mark(base);
+            }
+            Command::BlockValue => {
+                let base = self.pop_mark()?;
+                let value = if self.stack.len() > base {
+                    self.stack.pop()
+                } else {
+                    None
+                };
+                self.stack.truncate(base);
+                self.stack
+                    .push(value.unwrap_or_else(|| SunPointer::new(SunValue::Nil)));
+            }
+            Command::BlockExit => {
+                let base = self.pop_mark()?;
+                self.stack.truncate(base);
+            }
+            Command::Iter => {
+                let value = self.pop_value("iterable value")?;
+                match TableIter::new(value.clone()) {
+                    Some(iter) => self.frame().push_iter(SunIter::Table(iter)),
+                    None => {
+                        let e = SunError::TypeError(format!("`{}` is not iterable", value.get()));
+                        return Err(e);
+                    }
+                }
+            }
+            Command::IterRange => {
+                let start = self.pop_value("range start")?.get();
+                let end = self.pop_value("range end")?.get();
+                match (start, end) {
+                    (SunValue::Integer(start), SunValue::Integer(end)) => {
+                        self.frame().push_iter(SunIter::Range(start, end))
+                    }
+                    (start, end) => {
+                        let e = SunError::TypeError(format!(
+                            "range bounds must be integers but got `{start}` and `{end}`"
+                        ));
+                        return Err(e);
                     }
+                }
+            }
+            Command::IterNext(jump) => match self.frame().next_iter() {
+                Some(value) => self.stack.push(value),
+                None => *pc += *jump,
+            },
+            Command::IterEnd => self.frame().pop_iter(),
+            Command::Unpack(n) => self.unpack_array(*n, false, false)?,
+            Command::UnpackArray(n, rest, strict) => self.unpack_array(*n, *rest, *strict)?,
+            Command::UnpackKeys(keys, strict) => {
+                let value = self.pop_value("value to unpack")?;
+                let values = match &*value.borrow() {
+                    SunValue::Table(t) => keys
+                        .iter()
+                        .map(|key| match t.get_by_key(key) {
+                            Some(value) => Ok(value),
+                            None if *strict => Err(SunError::KeyError(format!(
+                                "failed to unpack missing key `{key}`"
+                            ))),
+                            None => Ok(SunPointer::new(SunValue::Nil)),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?,
+                    other => {
+                        let e = SunError::TypeError(format!("failed to unpack `{other}`"));
+                        return Err(e);
+                    }
+                };
+                self.stack.extend(values.into_iter().rev());
+            }
+            Command::SetIndex => {
+                let table = self.pop_value("table")?;
+                let key = self.pop_value("key")?;
+                let value = self.pop_value("new value")?;
+                let key = match TableKey::new(&key) {
+                    Some(key) => key,
                     None => {
-                        let e = SunError::RunError(format!(
-                            "stack is empty so failed to find get condition"
+                        let e = SunError::KeyError(format!("`{}` can't be a key", key.get()));
+                        return Err(e);
+                    }
+                };
+                let value = value.copy_value();
+                let mut target = table.borrow_mut();
+                match (&mut *target, &key) {
+                    (SunValue::Table(t), _) => t.set(key, value),
+                    (SunValue::Class(c), TableKey::String(name)) => {
+                        c.set_attribute(&String::from_utf8_lossy(name), value)
+                    }
+                    (other, _) => {
+                        let e = SunError::TypeError(format!(
+                            "`{other}` can't be assigned by index `{key}`"
                         ));
-                        error_output(e);
+                        return Err(e);
                     }
-                },
-                Command::Jump(jump) => {
-                    pc += jump;
-                }
-                Command::Back(jump) => {
-                    pc -= jump;
-                }
-                Command::SetTable => {
-                    let self_value = self.stack.pop();
-                    match self_value {
-                        Some(p) => {
-                            let mut pp = p.borrow_mut();
-                            match self.stack.pop() {
-                                Some(value) => {
-                                    *pp = value.get();
-                                }
-                                None => {
-                                    let e =
-                                        SunError::ParaError(format!("need new value but falied",));
-                                    error_output(e);
-                                }
-                            }
-                        }
-                        None => {
+                }
+            }
+            Command::LoadAttr => {
+                let object = self.pop_value("object")?;
+                let name = String::try_from(&self.pop_value("attribute name")?.get())?;
+                let attribute = match &*object.borrow() {
+                    SunValue::Class(c) => c.get_attribute(&name),
+                    SunValue::Table(t) => t.get_by_key(&name),
+                    _ => None,
+                };
+                match attribute {
+                    Some(attribute) => self.stack.push(attribute),
+                    None => {
+                        let meta_name = object.borrow().get_name().to_string();
+                        let method = self.get_method(&meta_name, &name)?;
+                        self.stack.push(SunPointer::new(SunValue::from(method)));
+                    }
+                }
+            }
+            Command::SetAttr => {
+                let object = self.pop_value("object")?;
+                let name = String::try_from(&self.pop_value("attribute name")?.get())?;
+                let value = self.pop_value("new value")?.copy_value();
+                let mut target = object.borrow_mut();
+                match &mut *target {
+                    SunValue::Class(c) => c.set_attribute(&name, value),
+                    SunValue::Table(t) => t.set(TableKey::from(name), value),
+                    other => {
+                        let e = SunError::AttributeError(format!(
+                            "failed to set attribute `{name}` for type `{}`",
+                            other.get_name()
+                        ));
+                        return Err(e);
+                    }
+                }
+            }
+            Command::NewInstance(name) => {
+                let instance = Class::new(&self.class_key(name), HashMap::new());
+                self.stack.push(SunPointer::new(SunValue::from(instance)));
+            }
+            Command::DefineClass(name, base, methods) => {
+                let constructor = self.pop_value("constructor")?;
+                let key = self.class_key(name);
+                let base = self.class_base(name, &key, base.as_deref())?;
+                let mut class = SunClass::new(&key, base);
+                for method in methods {
+                    match self.pop_value("method")?.get() {
+                        SunValue::Function(f) => class.get_meta_mut().set_method(method, f),
+                        other => {
                             let e = SunError::RunError(format!(
-                                "stack is empty so failed to find object"
+                                "method `{method}` of class `{name}` is `{other}`"
                             ));
-                            error_output(e);
+                            return Err(e);
                         }
                     }
                 }
-                Command::CreateTable(n) => {
-                    let mut table = Table::new();
-                    for _ in 1..=*n {
+                self.meta_map.insert(key, class.get_meta().clone());
+                self.stack.push(constructor);
+            }
+            Command::SetTable => {
+                let self_value = self.stack.pop();
+                match self_value {
+                    Some(p) => {
+                        let mut pp = p.borrow_mut();
                         match self.stack.pop() {
-                            Some(p) => {
-                                let value = p.get();
-                                match value {
-                                    SunValue::Table(ref t) => match t.get_by_idx(0) {
-                                        Some(p) => {
-                                            let content = p.get();
-                                            if let s @ SunValue::String(_) = content {
-                                                if s == "pair".into() {
-                                                    table.extend(t.clone())
-                                                }
-                                            } else {
-                                                table.append(value)
-                                            }
-                                        }
-                                        None => table.append(value),
-                                    },
-                                    _ => table.append(value),
-                                }
+                            Some(value) => {
+                                *pp = value.get();
                             }
                             None => {
-                                let e = SunError::RunError(format!(
-                                    "stack is empty so failed to set value for table"
-                                ));
-                                error_output(e);
+                                let e =
+                                    SunError::ParaError("need new value but falied".to_string());
+                                return Err(e);
                             }
                         }
                     }
-                    self.stack.push(SunPointer::new(SunValue::from(table)));
+                    None => {
+                        let e = SunError::RunError(
+                            "stack is empty so failed to find object".to_string(),
+                        );
+                        return Err(e);
+                    }
                 }
-                Command::SetPair(key) => {
-                    let mut table = Table::new();
+            }
+            Command::CreateTable(n) => {
+                let mut table = Table::new();
+                for _ in 1..=*n {
                     match self.stack.pop() {
-                        Some(p) => {
-                            let value = p.get();
-                            table.append(SunValue::from("pair"));
-                            table.append_kv(key.to_owned(), value);
-                            self.stack.push(SunPointer::new(SunValue::from(table)));
-                        }
+                        Some(p) => table.append(p.get()),
                         None => {
-                            let e = SunError::RunError(format!(
-                                "stack is empty so failed to set value for key `{key}`"
-                            ));
-                            error_output(e);
+                            let e = SunError::RunError(
+                                "stack is empty so failed to set value for table".to_string(),
+                            );
+                            return Err(e);
                         }
                     }
                 }
-                Command::Call(n) => match self.stack.pop() {
-                    Some(p) => {
-                        let value = p.get();
-                        match value {
-                            SunValue::Function(f) => match f {
-                                Function::RustFunction(rf) => {
-                                    let mut args = Vec::new();
-                                    for i in 1..=*n {
-                                        if let Some(arg) = self.stack.pop() {
-                                            args.push(arg)
-                                        } else {
-                                            let e = SunError::CallError(format!(
-                                                "need `{n}` but provide `{i}` parameters",
-                                            ));
-                                            error_output(e);
-                                        }
-                                    }
-                                    let res = rf(args);
-                                    self.stack.extend(res);
-                                }
-                                Function::SysFunction(sf) => sf(self),
-                            },
-                            other => {
-                                let e =
-                                    SunError::CallError(format!("`{}` is not a function", other));
-                                error_output(e);
-                            }
+                self.stack.push(SunPointer::new(SunValue::from(table)));
+            }
+            Command::SetPair => {
+                let key = self.pop_value("key")?;
+                let value = self.pop_value("value")?;
+                let key = match TableKey::new(&key) {
+                    Some(key) => key,
+                    None => {
+                        let e = SunError::KeyError(format!("`{}` can't be a key", key.get()));
+                        return Err(e);
+                    }
+                };
+                match self
+                    .stack
+                    .last()
+                    .map(|table| table.borrow_mut())
+                    .as_deref_mut()
+                {
+                    Some(SunValue::Table(t)) => t.set(key, value.copy_value()),
+                    _ => {
+                        let e = SunError::RunError(
+                            "stack top is not a table so failed to set pair".to_string(),
+                        );
+                        return Err(e);
+                    }
+                }
+            }
+            Command::Call(n) => {
+                let callee = self.pop_value("function")?;
+                self.call_value(callee, *n)?;
+            }
+            Command::CallMethod(n) => {
+                let object = self.pop_value("object")?;
+                let name = String::try_from(&self.pop_value("method name")?.get())?;
+                let meta_name = object.borrow().get_name().to_string();
+                match self.get_method(&meta_name, &name) {
+                    Ok(method) => {
+                        self.stack.push(object);
+                        self.call(method, n + 1)?;
+                    }
+                    Err(e) => {
+                        let entry = match &*object.borrow() {
+                            SunValue::Table(t) => t.get_by_key(&name),
+                            SunValue::Class(c) => c.get_attribute(&name),
+                            _ => None,
+                        };
+                        match entry {
+                            Some(entry) => self.call_value(entry, *n)?,
+                            None => return Err(e),
                         }
                     }
-                    None => {
-                        let e = SunError::RunError(format!(
-                            "stack is empty so failed to find function"
-                        ));
-                        error_output(e);
+                }
+            }
+            Command::Import(path) => {
+                let namespace = self.include(path, span)?;
+                self.stack.push(namespace);
+            }
+        }
+        Ok(true)
+    }
+
+    /// 调用值 `n`: 栈上参数的个数，不是函数的值通过类型的 `call` 方法调用，值作为第一个参数
+    fn call_value(&mut self, callee: SunPointer, n: usize) -> Result<(), SunError> {
+        let value = callee.get();
+        match value {
+            SunValue::Function(f) => self.call(f, n),
+            other => match self.get_method(other.get_name(), "call") {
+                Ok(method) => {
+                    self.stack.push(callee);
+                    self.call(method, n + 1)
+                }
+                Err(_) => {
+                    let e = SunError::CallError(format!("`{}` is not a function", other));
+                    Err(e)
+                }
+            },
+        }
+    }
+
+    /// 调用函数 `n`: 栈上参数的个数
+    fn call(&mut self, function: Function, n: usize) -> Result<(), SunError> {
+        match function {
+            Function::RustFunction(rf) => {
+                let args = self.pop_args(n)?;
+                let res = rf(args)?;
+                self.stack.extend(res);
+            }
+            Function::NativeFunction(nf) => {
+                let args = self.pop_args(n)?;
+                let res = nf(args)?;
+                self.stack.extend(res);
+            }
+            Function::SysFunction(sf) => sf(self)?,
+            Function::UserFunction(uf) => {
+                let args = self.pop_args(n)?;
+                self.call_function(&uf, args, Vec::new())?;
+            }
+            Function::Closure(c) => {
+                let args = self.pop_args(n)?;
+                self.call_function(c.get_function(), args, c.get_upvalues())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 从栈上取出一个值 `what`: 值的用途，用于错误信息
+    fn pop_value(&mut self, what: &str) -> Result<SunPointer, SunError> {
+        match self.stack.pop() {
+            Some(value) => Ok(value),
+            None => {
+                let e = SunError::RunError(format!("stack is empty so failed to find {what}"));
+                Err(e)
+            }
+        }
+    }
+
+    /// 解构栈顶 `Table` 的数组部分 `n`: 值的个数 `rest`: 是否收集剩余的值 `strict`: 是否检查值的个数
+    fn unpack_array(&mut self, n: usize, rest: bool, strict: bool) -> Result<(), SunError> {
+        let value = self.pop_value("value to unpack")?;
+        let values = match &*value.borrow() {
+            SunValue::Table(t) => {
+                let array = t.array();
+                if strict && (array.len() < n || !rest && array.len() > n) {
+                    let e = SunError::IndexError(format!(
+                        "expected {n} values to unpack but got {}",
+                        array.len()
+                    ));
+                    return Err(e);
+                }
+                let mut values = (0..n)
+                    .map(|i| {
+                        t.get_by_idx(i)
+                            .unwrap_or_else(|| SunPointer::new(SunValue::Nil))
+                    })
+                    .collect::<Vec<_>>();
+                if rest {
+                    let mut others = Table::new();
+                    for value in array.iter().skip(n) {
+                        others.append(value.get());
                     }
-                },
-                Command::Import(lib_name) => self.include(lib_name),
+                    values.push(SunPointer::new(SunValue::from(others)));
+                }
+                values
+            }
+            other => {
+                let e = SunError::TypeError(format!("failed to unpack `{other}`"));
+                return Err(e);
+            }
+        };
+        self.stack.extend(values.into_iter().rev());
+        Ok(())
+    }
+
+    /// 离开有值的语句块，返回进入时栈的长度
+    fn pop_mark(&mut self) -> Result<usize, SunError> {
+        match self.frame().pop_mark() {
+            Some(base) => Ok(base),
+            None => {
+                let e = SunError::RunError("leave a block which was never entered".to_string());
+                Err(e)
             }
-            self.debug(pc, &command);
-            pc += 1;
         }
     }
 
-    /// debug 模式的打印信息处理 `pc`: 程序计数器 `command`: 当前运行的指令
-    fn debug(&self, pc: usize, command: &Command) {
-        if self.check_stack == true && self.is_debug == false {
+    /// 从栈上取出 `n` 个函数参数
+    fn pop_args(&mut self, n: usize) -> Result<Vec<SunPointer>, SunError> {
+        let mut args = Vec::new();
+        for i in 0..n {
+            if let Some(arg) = self.stack.pop() {
+                args.push(arg)
+            } else {
+                let e = SunError::CallError(format!("need `{n}` but provide `{i}` parameters"));
+                return Err(e);
+            }
+        }
+        Ok(args)
+    }
+
+    /// 调用脚本函数，函数体执行结束时栈顶的值作为返回值 `upvalues`: 闭包捕获的变量
+    fn call_function(
+        &mut self,
+        function: &UserFunction,
+        args: Vec<SunPointer>,
+        upvalues: Vec<Upvalue>,
+    ) -> Result<(), SunError> {
+        let params = function.get_params();
+        if params.len() != args.len() {
+            let e = SunError::CallError(format!(
+                "function `{}` need `{}` but provide `{}` parameters",
+                function.get_name(),
+                params.len(),
+                args.len()
+            ));
+            return Err(e);
+        }
+        if self.frames.len() >= self.max_depth {
+            let e = SunError::RunError(format!(
+                "maximum call depth `{}` exceeded when calling `{}`",
+                self.max_depth,
+                function.get_name()
+            ));
+            return Err(e);
+        }
+        let mut frame = Frame::new(
+            function.get_name(),
+            function.get_body(),
+            function.get_frame_size(),
+            upvalues,
+        );
+        for (slot, arg) in args.into_iter().enumerate() {
+            frame.store_local(slot, arg.copy_value());
+        }
+        // 模块中定义的函数使用该模块的全局变量表
+        let module = function
+            .get_module()
+            .filter(|module| self.modules.contains_key(*module))
+            .cloned();
+        let previous = self.switch_module(module);
+        self.frames
+            .push(frame.returns_to(self.stack.len(), previous));
+        Ok(())
+    }
+
+    /// debug 模式的打印信息处理 `pc`: 程序计数器 `command`: 当前运行的指令 `span`: 指令的源码位置
+    fn debug(&self, pc: usize, command: &Command, span: &Span) {
+        if self.check_stack && !self.is_debug {
             println!();
             debug_output(&self.stack, true);
             println!();
         }
-        if self.check_global == true && self.is_debug == false {
+        if self.check_global && !self.is_debug {
             println!();
             debug_output(&self.value_map, true);
             println!()
         }
-        if self.is_debug == true {
+        if self.is_debug {
             println!();
             debug_output(pc, false);
             debug_output(command, false);
+            debug_output(span, false);
             debug_output(&self.stack, true);
             debug_output(&self.value_map, true);
+            debug_output(&self.frames, true);
             debug_output(&self.meta_map, true);
             println!();
         }
     }
 
+    /// 脚本定义的类由内向外的继承链，不是脚本定义的类时返回 `None`
+    fn class_chain<'a>(&'a self, name: &'a str) -> Option<Vec<&'a str>> {
+        let mut chain = Vec::new();
+        let mut name = name;
+        loop {
+            chain.push(name);
+            match self.meta_map.get(name)?.get_base() {
+                SunBase::Other(base) if base == "Class" => return Some(chain),
+                SunBase::Other(base) => name = base,
+                _ => return None,
+            }
+        }
+    }
+
+    /// 脚本定义的类在元数据表中的键，模块中定义的类以模块路径限定，不同模块中的同名类互不影响
+    fn class_key(&self, name: &str) -> String {
+        match &self.module {
+            Some(module) => format!("{module}::{name}"),
+            None => name.to_string(),
+        }
+    }
+
+    /// 检查类定义并返回基类，内置类型不能被重新定义，基类必须是当前模块中定义的类且不能继承自身
+    /// `name`: 类名 `key`: 类在元数据表中的键
+    fn class_base(&self, name: &str, key: &str, base: Option<&str>) -> Result<SunBase, SunError> {
+        if self.meta_map.contains_key(name) && self.class_chain(name).is_none() {
+            let e = SunError::TypeError(format!("built-in type `{name}` can't be redefined"));
+            return Err(e);
+        }
+        let (base, base_key) = match base {
+            Some(base) => (base, self.class_key(base)),
+            None => return Ok(SunBase::Other("Class".to_string())),
+        };
+        match self.class_chain(&base_key) {
+            Some(chain) if chain.contains(&key) => {
+                let e = SunError::TypeError(format!("class `{name}` can't inherit from itself"));
+                Err(e)
+            }
+            Some(_) => Ok(SunBase::Other(base_key)),
+            None => {
+                let e = SunError::TypeError(format!("`{base}` is not a class"));
+                Err(e)
+            }
+        }
+    }
+
     /// 递归查找基类的方法
-    fn get_method(&self, meta_name: &str, method_name: &str) -> Function {
+    fn get_method(&self, meta_name: &str, method_name: &str) -> Result<Function, SunError> {
         match self.meta_map.get(meta_name) {
             Some(meta) => match meta.get_method(method_name) {
-                Some(method) => method,
+                Some(method) => Ok(method),
                 None => match meta.get_base() {
                     &SunBase::None => {
                         let e = SunError::AttributeError(format!(
                             "failed to find attribute `{}` for type `{}`",
                             method_name, meta_name
                         ));
-                        error_output(e);
+                        Err(e)
                     }
                     &SunBase::Object => self.get_method("Object", method_name),
-                    &SunBase::Other(ref c) => self.get_method(c, method_name),
+                    SunBase::Other(c) => self.get_method(c, method_name),
                 },
             },
             None => {
                 let e = SunError::TypeError(format!("`{meta_name}` is not a valid sun type"));
-                error_output(e);
+                Err(e)
             }
         }
     }
 
-    fn include(&mut self, lib_name: &str) {
-        dbg!(lib_name);
+    /// 导入模块，每个模块只执行一次，返回模块顶层变量组成的 `Table`
+    /// `span`: 导入语句的位置，相对路径从该位置所在文件的目录开始查找
+    fn include(&mut self, path: &str, span: &Span) -> Result<SunPointer, SunError> {
+        if let Some(export_lib) = self.libs.get(path).cloned() {
+            return self.include_lib(path, export_lib);
+        }
+        let search = module::search_paths(self.search_paths.as_deref(), env::var_os("SUN_PATH"));
+        let file = module::resolve(path, &span.file, &search)?;
+        let key: Rc<str> = Rc::from(file.to_string_lossy().as_ref());
+        if let Some(start) = self.loading.iter().position(|module| *module == key) {
+            let chain = self.loading[start..]
+                .iter()
+                .chain([&key])
+                .map(|module| module.as_ref())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            let e = SunError::ImportError(format!("circular import `{chain}`"));
+            return Err(e);
+        }
+        if let Some(namespace) = self.modules.get(&key).and_then(|m| m.namespace.clone()) {
+            return Ok(namespace);
+        }
+        let input = File::open(&file)
+            .map_err(|e| SunError::ImportError(format!("failed to open module `{path}` ({e})")))?;
+        let proto = ParseProto::new(BufReader::new(input), &key, false, false, false)?;
+        self.modules
+            .insert(key.clone(), Module::new(self.builtins.clone()));
+        self.loading.push(key.clone());
+        let previous = self.switch_module(Some(key.clone()));
+        let base = self.stack.len();
+        let code = Rc::clone(&proto.commands);
+        self.frames
+            .push(Frame::new("main", code, proto.frame_size, Vec::new()));
+        let res = self.execute();
+        self.frames.pop();
+        self.stack.truncate(base);
+        let namespace = self.namespace();
+        self.switch_module(previous);
+        self.loading.pop();
+        match res {
+            Ok(()) => {
+                if let Some(module) = self.modules.get_mut(&key) {
+                    module.namespace = Some(namespace.clone());
+                }
+                Ok(namespace)
+            }
+            // 导入失败的模块下次导入时重新执行
+            Err(e) => {
+                self.modules.remove(&key);
+                Err(e)
+            }
+        }
+    }
+
+    /// 导入原生库，库定义的类型合并到 meta 表，导出的值组成库的 `Table`
+    /// 同一个库以不同的名字导入时返回同一个 `Table`
+    fn include_lib(
+        &mut self,
+        name: &str,
+        export_lib: Rc<dyn Fn() -> ExportLib>,
+    ) -> Result<SunPointer, SunError> {
+        let loaded = self
+            .libs
+            .iter()
+            .filter(|(_, lib)| Rc::ptr_eq(lib, &export_lib))
+            .find_map(|(other, _)| self.modules.get(other.as_str())?.namespace.clone());
+        if let Some(namespace) = loaded {
+            return Ok(namespace);
+        }
+        let lib = export_lib();
+        if let Some(meta) = lib
+            .meta
+            .keys()
+            .find(|meta| self.meta_map.contains_key(*meta))
+        {
+            let e = SunError::ImportError(format!(
+                "type `{meta}` of library `{name}` is already defined"
+            ));
+            return Err(e);
+        }
+        self.meta_map.extend(lib.meta);
+        let mut table = Table::new();
+        for (key, value) in lib.value {
+            table.set(TableKey::from(key), value);
+        }
+        let namespace = SunPointer::new(SunValue::from(table));
+        let mut module = Module::new(HashMap::new());
+        module.namespace = Some(namespace.clone());
+        self.modules.insert(Rc::from(name), module);
+        Ok(namespace)
+    }
+
+    /// 当前全局变量表中除预导入的值以外的变量组成的 `Table`
+    fn namespace(&self) -> SunPointer {
+        let mut table = Table::new();
+        for (name, value) in &self.value_map {
+            let builtin = self
+                .builtins
+                .get(name)
+                .is_some_and(|builtin| Rc::ptr_eq(builtin, value));
+            if !builtin {
+                table.set(TableKey::from(name.as_str()), value.clone());
+            }
+        }
+        SunPointer::new(SunValue::from(table))
+    }
+
+    /// 切换到模块 `module` 的全局变量表，`None` 为主程序，返回之前所在的模块
+    fn switch_module(&mut self, module: Option<Rc<str>>) -> Option<Rc<str>> {
+        if module == self.module {
+            return module;
+        }
+        let globals = match &module {
+            Some(name) => match self.modules.get_mut(name) {
+                Some(module) => std::mem::take(&mut module.globals),
+                None => return self.module.clone(),
+            },
+            None => std::mem::take(&mut self.main_globals),
+        };
+        let globals = std::mem::replace(&mut self.value_map, globals);
+        match &self.module {
+            Some(name) => {
+                if let Some(module) = self.modules.get_mut(name) {
+                    module.globals = globals;
+                }
+            }
+            None => self.main_globals = globals,
+        }
+        std::mem::replace(&mut self.module, module)
     }
 }
 
-impl<'a> IsMachine for VirtualMachine<'a> {
+impl IsMachine for VirtualMachine {
     fn pop(&mut self) -> Option<SunPointer> {
         self.stack.pop()
     }
@@ -334,11 +1030,7 @@ impl<'a> IsMachine for VirtualMachine<'a> {
     }
 
     fn get_meta(&self, name: &str) -> Option<Vec<&str>> {
-        if let Some(meta) = self.meta_map.get(name) {
-            Some(meta.get_methods())
-        } else {
-            None
-        }
+        self.meta_map.get(name).map(|meta| meta.get_methods())
     }
 
     fn show_global(&self) {
@@ -352,3 +1044,762 @@ impl<'a> IsMachine for VirtualMachine<'a> {
         log_output(variables)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::VirtualMachine;
+    use crate::{parser::parser::ParseProto, vm::command::Command};
+    use std::{cell::Cell, collections::HashMap, rc::Rc};
+    use sun_core::{
+        container::{Class, Function, RustFunction, SunValue, Table, UserData},
+        meta::{SunBase, SunMeta},
+        utils::{SunError, SunPointer},
+        ExportLib, ImportAble,
+    };
+
+    fn run(src: &str) -> VirtualMachine {
+        let mut vm = VirtualMachine::new(false, false, false);
+        let proto = ParseProto::new(src.as_bytes(), "<test>", false, false, false).unwrap();
+        vm.run(&proto).unwrap();
+        vm
+    }
+
+    fn global(vm: &VirtualMachine, name: &str) -> SunValue {
+        vm.value_map[name].get()
+    }
+
+    #[test]
+    fn test_user_function() {
+        let vm = run("fn area(w, h) { w * h }; s = area(3, 4); fn nothing() { }; n = nothing()");
+        assert_eq!(global(&vm, "s"), SunValue::from(12));
+        assert!(matches!(global(&vm, "n"), SunValue::Nil));
+        assert!(!vm.value_map.contains_key("w"));
+    }
+
+    #[test]
+    fn test_jump() {
+        let vm = run("i = 0; n = 0;
+            while i < 10 {
+                i = i + 1;
+                if i == 3 { continue };
+                if i > 6 { break };
+                j = 0;
+                while T { j = j + 1; if j == 2 { break } };
+                n = n + j
+            };
+            fn first(t) { k = 0; while T { if t[k] > 4 { -> t[k] }; k = k + 1 } };
+            f = first({1, 5, 9})");
+        assert_eq!(global(&vm, "i"), SunValue::from(7));
+        assert_eq!(global(&vm, "n"), SunValue::from(10));
+        assert_eq!(global(&vm, "f"), SunValue::from(5));
+    }
+
+    #[test]
+    fn test_jump_offset() {
+        let src = "while a { if b { break } else { continue } }";
+        let proto = ParseProto::new(src.as_bytes(), "<test>", false, false, false).unwrap();
+        let jumps: Vec<String> = proto
+            .commands
+            .iter()
+            .filter_map(|(command, _)| match command {
+                Command::TestJump(_) | Command::Jump(_) | Command::Back(_) => {
+                    Some(format!("{command:?}"))
+                }
+                _ => None,
+            })
+            .collect();
+        // 0 LoadValue(a) 1 TestJump 2 LoadValue(b) 3 TestJump 4 Jump(break) 5 Jump(if) 6 Back(continue) 7 Back
+        assert_eq!(
+            jumps,
+            [
+                "TestJump(6)",
+                "TestJump(2)",
+                "Jump(3)",
+                "Jump(1)",
+                "Back(7)",
+                "Back(8)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_local_scope() {
+        let vm = run("i = 100;
+            fn sum(t) { let i = 0; let s = 0; while i < t.alen() { s = s + t[i]; i = i + 1 }; s };
+            s = sum({1, 2, 3});
+            k = 0;
+            while k < 3 { let j = k; k = k + 1 };
+            n = 0; fn inc() { n += 1 }; m = inc(); m = inc()");
+        assert_eq!(global(&vm, "i"), SunValue::from(100));
+        assert_eq!(global(&vm, "n"), SunValue::from(2));
+        assert_eq!(global(&vm, "s"), SunValue::from(6));
+        assert_eq!(global(&vm, "k"), SunValue::from(3));
+        assert!(!vm.value_map.contains_key("j"));
+    }
+
+    #[test]
+    fn test_closure() {
+        let vm = run("fn make_counter() {
+                let count = 0;
+                fn counter() { count = count + 1; count };
+                counter
+            };
+            c = make_counter();
+            c(); c();
+            a = c();
+            d = make_counter();
+            b = d();
+            fn outer(x) { fn () { fn () { x } } };
+            g = outer(5);
+            h = g();
+            x = h()");
+        assert_eq!(global(&vm, "a"), SunValue::from(3));
+        assert_eq!(global(&vm, "b"), SunValue::from(1));
+        assert_eq!(global(&vm, "x"), SunValue::from(5));
+    }
+
+    #[test]
+    fn test_error() {
+        let mut vm = VirtualMachine::new(false, false, false);
+        let parse =
+            |src: &'static str| ParseProto::new(src.as_bytes(), "<test>", false, false, false);
+        assert_eq!(parse("a = (1").unwrap_err().kind(), "SymbolError");
+        assert_eq!(parse("a = 1 $ 2").unwrap_err().kind(), "SymbolError");
+        let proto = parse("fn f(t) { t[5] }; a = 1; b = f({1})").unwrap();
+        match vm.run(&proto) {
+            Err(SunError::TraceError(e, trace)) => {
+                assert!(matches!(*e, SunError::IndexError(_)));
+                let trace: Vec<String> = trace
+                    .iter()
+                    .map(|(span, name)| format!("{span} {name}"))
+                    .collect();
+                assert_eq!(trace, ["<test>:1:12 f", "<test>:1:31 main"]);
+            }
+            other => panic!("unexpected result {other:?}"),
+        }
+        assert!(vm.stack.is_empty() && vm.frames.is_empty());
+        vm.run(&parse("c = a + 1").unwrap()).unwrap();
+        assert_eq!(global(&vm, "c"), SunValue::from(2));
+    }
+
+    #[test]
+    fn test_diagnostic() {
+        let diagnose = |src: &'static str| match ParseProto::new(
+            src.as_bytes(),
+            "<test>",
+            false,
+            false,
+            false,
+        ) {
+            Err(SunError::SourceError(e, d)) => (e.kind(), d.span.to_string(), d.source, d.help),
+            other => panic!("unexpected result {other:?}"),
+        };
+        let (kind, span, source, help) = diagnose("a = 1;\nb = (2 + 3\n");
+        assert_eq!(
+            (kind, span.as_str(), source.as_str()),
+            ("SymbolError", "<test>:2:11", "b = (2 + 3")
+        );
+        assert!(help.is_none());
+        let (kind, span, source, _) = diagnose("x = 12ab; y = 1");
+        assert_eq!(
+            (kind, span.as_str(), source.as_str()),
+            ("NumberError", "<test>:1:7", "x = 12ab; y = 1")
+        );
+        let (kind, span, _, help) = diagnose("a = 1;\n1 = 2");
+        assert_eq!((kind, span.as_str()), ("AssignError", "<test>:2:3"));
+        assert!(help.is_some());
+        let (kind, span, _, _) = diagnose("t = {};\nx = t.\"k\"");
+        assert_eq!((kind, span.as_str()), ("AttributeError", "<test>:2:7"));
+        // 出错位置只在诊断信息中给出一次
+        let e = ParseProto::new("a = 1;\nb = a +".as_bytes(), "<test>", false, false, false)
+            .unwrap_err();
+        assert!(matches!(e, SunError::SourceError(..)));
+        assert!(!e.to_string().contains("at line"));
+    }
+
+    #[test]
+    fn test_try() {
+        let vm = run("t = {1};
+            try { a = t[\"x\"] } catch e { k = e[\"kind\"] };
+            fn check(n) { if n > 2 { error(\"too big\") }; n };
+            try { r = check(1); r = check(5) } catch e { m = e[\"message\"] };
+            n = 0;
+            while n < 3 { try { n = n + 1; if n == 2 { continue }; t[9] } catch e { } };
+            try { try { error(1) } catch e { error(2) } } catch e { o = e[\"message\"] }");
+        assert_eq!(global(&vm, "k"), SunValue::from("KeyError"));
+        assert_eq!(global(&vm, "r"), SunValue::from(1));
+        assert_eq!(global(&vm, "m"), SunValue::from("too big"));
+        assert_eq!(global(&vm, "n"), SunValue::from(3));
+        assert_eq!(global(&vm, "o"), SunValue::from("2"));
+        assert!(!vm.value_map.contains_key("e"));
+        assert!(vm.stack.is_empty());
+    }
+
+    #[test]
+    fn test_for() {
+        let vm = run("t = {1, 2, 3}; s = 0; for x in t { s = s + x };
+            d = {\"b\": 2, \"a\": 1}; ks = {}; vs = 0;
+            for k, v in d.pairs() { ks.push(k); vs = vs + v };
+            n = 0; for i in 0..10 { if i == 5 { break }; if i == 1 { continue }; n = n + i };
+            c = 0; for i in 0..3 { for j in 1..3 { c = c + 1 } };
+            try { for x in t { error(x) } } catch e { m = e[\"message\"] };
+            fn find(t, y) { for x in t { if x == y { -> T } }; F };
+            f = find(t, 2)");
+        assert_eq!(global(&vm, "s"), SunValue::from(6));
+        let mut ks = Table::new();
+        ks.append(SunValue::from("a"));
+        ks.append(SunValue::from("b"));
+        assert_eq!(global(&vm, "ks"), SunValue::from(ks));
+        assert_eq!(global(&vm, "vs"), SunValue::from(3));
+        assert_eq!(global(&vm, "n"), SunValue::from(9));
+        assert_eq!(global(&vm, "c"), SunValue::from(6));
+        assert_eq!(global(&vm, "m"), SunValue::from("1"));
+        assert_eq!(global(&vm, "f"), SunValue::from(true));
+        assert!(!vm.value_map.contains_key("x") && !vm.value_map.contains_key("i"));
+        assert!(vm.stack.is_empty());
+    }
+
+    #[test]
+    fn test_format_string() {
+        let vm = run("x = 41; t = {\"name\": \"sun\"};
+            s = f\"total: {1 + x}, name: {t[\"name\"]}\";
+            fn g(a) { let b = \"!\"; f\"{a}{{}}{f\"<{b}>\"}\" };
+            r = g(T); e = f\"\"");
+        assert_eq!(global(&vm, "s"), SunValue::from("total: 42, name: sun"));
+        assert_eq!(global(&vm, "x"), SunValue::from(41));
+        assert_eq!(global(&vm, "r"), SunValue::from("true{}<!>"));
+        assert_eq!(global(&vm, "e"), SunValue::from(""));
+    }
+
+    #[test]
+    fn test_integer() {
+        let vm = run(
+            "a = 7 / 2; b = 7.0 / 2; c = 1 + 2.5; d = 9007199254740993 + 0;
+            x = -7; e = x.floordiv(2); y = 7.5; f = y.floordiv(2); g = -7 % 3;
+            fd = -7 ~/ 2; ff = 7.5 ~/ -2; fm = 7 % -2; fr = -7.5 % 2; // 注释
+            same = 7 == -2 * (7 ~/ -2) + 7 % -2;
+            try { 9223372036854775807 + 1 } catch err { k = err[\"kind\"] };
+            try { 1 / 0 } catch err { z = err[\"kind\"] };
+            t = {10, 20}; i = t[1]; n = t.alen();
+            p = 2; q = p.pow(10); h = p.pow(-1); r = 5; u = r.fac();
+            try { p.pow(64) } catch err { o = err[\"kind\"] };
+            try { x.fac() } catch err { v = err[\"kind\"] }",
+        );
+        assert_eq!(global(&vm, "a"), SunValue::from(3));
+        assert_eq!(global(&vm, "b"), SunValue::from(3.5));
+        assert_eq!(global(&vm, "c"), SunValue::from(3.5));
+        assert_eq!(global(&vm, "d"), SunValue::from(9007199254740993));
+        assert_eq!(global(&vm, "e"), SunValue::from(-4));
+        assert_eq!(global(&vm, "f"), SunValue::from(3.0));
+        assert_eq!(global(&vm, "g"), SunValue::from(2));
+        assert_eq!(global(&vm, "fd"), SunValue::from(-4));
+        assert_eq!(global(&vm, "ff"), SunValue::from(-4.0));
+        assert_eq!(global(&vm, "fm"), SunValue::from(-1));
+        assert_eq!(global(&vm, "fr"), SunValue::from(0.5));
+        assert_eq!(global(&vm, "same"), SunValue::from(true));
+        assert_eq!(global(&vm, "k"), SunValue::from("ArithmeticError"));
+        assert_eq!(global(&vm, "z"), SunValue::from("ArithmeticError"));
+        assert_eq!(global(&vm, "i"), SunValue::from(20));
+        assert_eq!(global(&vm, "n"), SunValue::from(2));
+        assert_eq!(global(&vm, "q"), SunValue::from(1024));
+        assert_eq!(global(&vm, "h"), SunValue::from(0.5));
+        assert_eq!(global(&vm, "u"), SunValue::from(120));
+        assert_eq!(global(&vm, "o"), SunValue::from("ArithmeticError"));
+        assert_eq!(global(&vm, "v"), SunValue::from("ParaError"));
+    }
+
+    #[test]
+    fn test_operand_types() {
+        let vm = run("a = 1 == \"a\"; b = 1 != \"a\"; c = true == 1;
+            try { 1 + \"a\" } catch err { k = err[\"kind\"]; m = err[\"message\"] };
+            try { 1 < nil } catch err { l = err[\"kind\"] };
+            try { true && 1 } catch err { n = err[\"kind\"] }");
+        assert_eq!(global(&vm, "a"), SunValue::from(false));
+        assert_eq!(global(&vm, "b"), SunValue::from(true));
+        assert_eq!(global(&vm, "c"), SunValue::from(false));
+        assert_eq!(global(&vm, "k"), SunValue::from("TypeError"));
+        assert_eq!(
+            global(&vm, "m"),
+            SunValue::from("unsupported operand types for `+`: `Integer` and `String`")
+        );
+        assert_eq!(global(&vm, "l"), SunValue::from("TypeError"));
+        assert_eq!(global(&vm, "n"), SunValue::from("TypeError"));
+    }
+
+    #[test]
+    fn test_call_depth() {
+        // 脚本函数的调用不占用原生栈，测试线程默认大小的栈即可运行
+        let vm = run("fn down(n) { down(n + 1) };
+            try { down(0) } catch err { k = err[\"kind\"] };
+            fn sum(n) { if n == 0 { -> 0 }; -> n + sum(n - 1) }; s = sum(250);
+            fn count(n) { n == 0 ? 0 : count(n - 1) + 1 }; c = count(5000)");
+        assert_eq!(global(&vm, "k"), SunValue::from("RunError"));
+        assert_eq!(global(&vm, "s"), SunValue::from(31375));
+        assert_eq!(global(&vm, "c"), SunValue::from(5000));
+        assert!(vm.stack.is_empty() && vm.frames.is_empty());
+        let mut vm = VirtualMachine::new(false, false, false);
+        vm.set_max_depth(50);
+        let src = "fn count(n) { n == 0 ? 0 : count(n - 1) + 1 }; a = count(40);
+            try { count(60) } catch err { k = err[\"kind\"] }";
+        let proto = ParseProto::new(src.as_bytes(), "<test>", false, false, false).unwrap();
+        vm.run(&proto).unwrap();
+        assert_eq!(global(&vm, "a"), SunValue::from(40));
+        assert_eq!(global(&vm, "k"), SunValue::from("RunError"));
+    }
+
+    #[test]
+    fn test_meta_arity() {
+        let vm = run(
+            "b = T; try { b.xor() } catch err { k1 = err[\"kind\"]; m = err[\"message\"] };
+            x = 1; try { x.add() } catch err { k2 = err[\"kind\"] };
+            try { x.floordiv() } catch err { k3 = err[\"kind\"] };
+            s = \"a\"; try { s.add() } catch err { k4 = err[\"kind\"] };
+            n = nil; try { n.eq() } catch err { k5 = err[\"kind\"] };
+            t = {1}; try { t.index() } catch err { k6 = err[\"kind\"] };
+            class A {}; a = A(); try { a.eq() } catch err { k7 = err[\"kind\"] };
+            try { a.index() } catch err { k8 = err[\"kind\"] }",
+        );
+        assert_eq!(global(&vm, "k1"), SunValue::from("ParaError"));
+        assert_eq!(global(&vm, "k2"), SunValue::from("ParaError"));
+        assert_eq!(global(&vm, "k3"), SunValue::from("ParaError"));
+        assert_eq!(global(&vm, "k4"), SunValue::from("ParaError"));
+        assert_eq!(global(&vm, "k5"), SunValue::from("ParaError"));
+        assert_eq!(global(&vm, "k6"), SunValue::from("ParaError"));
+        assert_eq!(global(&vm, "k7"), SunValue::from("ParaError"));
+        assert_eq!(global(&vm, "k8"), SunValue::from("ParaError"));
+        assert_eq!(
+            global(&vm, "m"),
+            SunValue::from("need `1` but provide `0` parameters")
+        );
+    }
+
+    #[test]
+    fn test_compound_assign() {
+        let vm = run("a = 0; b = a + 1; c = -b;
+            i = 10; i += 5; i -= 1; i *= 2; i /= 4; i %= 4; j = 7; j ~/= -2;
+            s = \"ab\"; s += \"c\"; t = {1, 2}; t[0] += 10; f = T ^^ T; p = 3; p ^= 2;
+            w = -2 ^ 2; z = 2 ^ 3 ^ 2; y = 2 ^ -1;
+            fn mk() { let n = 0; fn () { n += 1; n } }; k = mk(); k(); m = k()");
+        assert_eq!(global(&vm, "a"), SunValue::from(0));
+        assert_eq!(global(&vm, "b"), SunValue::from(1));
+        assert_eq!(global(&vm, "c"), SunValue::from(-1));
+        assert_eq!(global(&vm, "i"), SunValue::from(3));
+        assert_eq!(global(&vm, "j"), SunValue::from(-4));
+        assert_eq!(global(&vm, "s"), SunValue::from("abc"));
+        let mut t = Table::new();
+        t.append(SunValue::from(11));
+        t.append(SunValue::from(2));
+        assert_eq!(global(&vm, "t"), SunValue::from(t));
+        assert_eq!(global(&vm, "f"), SunValue::from(false));
+        assert_eq!(global(&vm, "p"), SunValue::from(9));
+        assert_eq!(global(&vm, "w"), SunValue::from(-4));
+        assert_eq!(global(&vm, "z"), SunValue::from(512));
+        assert_eq!(global(&vm, "y"), SunValue::from(0.5));
+        assert_eq!(global(&vm, "m"), SunValue::from(2));
+    }
+
+    #[test]
+    fn test_if_expr() {
+        let vm = run("x = 3;
+            a = if x > 2 { y = x * 2; y + 1 } else { 0 };
+            b = (if x > 5 { 1 }) ? 1 : 2;
+            c = x > 5 ? \"big\" : x > 2 ? \"mid\" : \"small\";
+            fn abs(n) { n < 0 ? -n : n };
+            d = abs(-4) + (x == 3 ? 10 : 20);
+            n = 0;
+            for i in 0..10 { n += if i > 2 { if i == 6 { break }; 1 } else { continue } };
+            try { e = if T { error(\"oops\") } else { 1 } } catch err { e = 2 }");
+        assert_eq!(global(&vm, "a"), SunValue::from(7));
+        assert_eq!(global(&vm, "b"), SunValue::from(2));
+        assert_eq!(global(&vm, "c"), SunValue::from("mid"));
+        assert_eq!(global(&vm, "d"), SunValue::from(14));
+        assert_eq!(global(&vm, "n"), SunValue::from(3));
+        assert_eq!(global(&vm, "e"), SunValue::from(2));
+        assert!(vm.stack.is_empty());
+    }
+
+    #[test]
+    fn test_match() {
+        let vm = run("fn kind(v) {
+                match v {
+                    1 => \"one\",
+                    \"a\" | \"b\" => \"letter\",
+                    2..10 => \"small\",
+                    {\"kind\": \"circle\", \"r\": r} => f\"circle {r}\",
+                    {\"kind\": k} => k,
+                    nil => \"nil\",
+                    _ => { let s = \"other\"; s }
+                }
+            };
+            a = kind(1); b = kind(\"b\"); c = kind(5); d = kind({\"kind\": \"circle\", \"r\": 2});
+            e = kind({\"kind\": \"square\"}); f = kind(nil); g = kind({1}); h = kind(1.0); i = kind(9.5); j = kind(10); k = kind(\"c\");
+            n = 0;
+            for i in 0..6 { n += match i { 0 | 1 => 10, 4 => { break }, _ => 1 } };
+            m = match \"x\" { \"y\" => 1 } ? 1 : 2");
+        assert_eq!(global(&vm, "a"), SunValue::from("one"));
+        assert_eq!(global(&vm, "b"), SunValue::from("letter"));
+        assert_eq!(global(&vm, "c"), SunValue::from("small"));
+        assert_eq!(global(&vm, "d"), SunValue::from("circle 2"));
+        assert_eq!(global(&vm, "e"), SunValue::from("square"));
+        assert_eq!(global(&vm, "f"), SunValue::from("nil"));
+        assert_eq!(global(&vm, "g"), SunValue::from("other"));
+        assert_eq!(global(&vm, "h"), SunValue::from("one"));
+        assert_eq!(global(&vm, "i"), SunValue::from("small"));
+        assert_eq!(global(&vm, "j"), SunValue::from("other"));
+        assert_eq!(global(&vm, "k"), SunValue::from("other"));
+        assert_eq!(global(&vm, "n"), SunValue::from(22));
+        assert_eq!(global(&vm, "m"), SunValue::from(2));
+        assert!(vm.stack.is_empty());
+    }
+
+    #[test]
+    fn test_unpack() {
+        let vm = run("a = 1; b = 2; a, b = b, a;
+            x, y, z = 10, 20;
+            point = {\"x\": 3, \"y\": 4};
+            {y, \"x\": px} = point;
+            list = {1, 2, 3};
+            [first, ...rest] = list;
+            rest[0] = 9;
+            [c, d, e] = {5};
+            fn sum(t) { let h = 0; let tail = 0; [h, ...tail] = t; h + tail.alen() };
+            s = sum(list);
+            try { [p, q] = list! } catch err { k1 = err[\"kind\"] };
+            try { {w} = point! } catch err { k2 = err[\"kind\"] }");
+        assert_eq!(global(&vm, "a"), SunValue::from(2));
+        assert_eq!(global(&vm, "b"), SunValue::from(1));
+        assert_eq!(global(&vm, "x"), SunValue::from(10));
+        assert!(matches!(global(&vm, "z"), SunValue::Nil));
+        assert_eq!(global(&vm, "px"), SunValue::from(3));
+        assert_eq!(global(&vm, "y"), SunValue::from(4));
+        assert_eq!(global(&vm, "first"), SunValue::from(1));
+        let mut t = Table::new();
+        t.append(SunValue::from(9));
+        t.append(SunValue::from(3));
+        assert_eq!(global(&vm, "rest"), SunValue::from(t));
+        assert_eq!(global(&vm, "c"), SunValue::from(5));
+        assert!(matches!(global(&vm, "d"), SunValue::Nil));
+        assert_eq!(global(&vm, "s"), SunValue::from(3));
+        assert_eq!(global(&vm, "k1"), SunValue::from("IndexError"));
+        assert_eq!(global(&vm, "k2"), SunValue::from("KeyError"));
+    }
+
+    #[test]
+    fn test_value_semantics() {
+        let vm = run("t = {1, 2}; x = t[0]; t[0] = 5; u = t; u.push(3);
+            fn inc(n) { n += 1; n }; a = 1; b = inc(a); c = a.add(2);
+            p = T; q = !p; s = \"a\"; r = s + \"b\"");
+        assert_eq!(global(&vm, "x"), SunValue::from(1));
+        let mut t = Table::new();
+        t.append(SunValue::from(5));
+        t.append(SunValue::from(2));
+        t.append(SunValue::from(3));
+        assert_eq!(global(&vm, "t"), SunValue::from(t));
+        assert_eq!(global(&vm, "a"), SunValue::from(1));
+        assert_eq!(global(&vm, "b"), SunValue::from(2));
+        assert_eq!(global(&vm, "c"), SunValue::from(3));
+        assert_eq!(global(&vm, "p"), SunValue::from(true));
+        assert_eq!(global(&vm, "s"), SunValue::from("a"));
+        assert_eq!(global(&vm, "r"), SunValue::from("ab"));
+    }
+
+    #[test]
+    fn test_table_key() {
+        let vm = run("k = {}; t = {1, 2}; t[T] = \"yes\"; t[10] = \"ten\";
+            t[k] = \"table\"; t[\"n\"] = 1; t[\"n\"] += 1; i = 0;
+            a = t[i + 1]; b = t[T]; c = t[10]; d = t[k]; e = t[\"n\"]; f = t[1.0];
+            g = match 10 { 10 => \"hit\", _ => \"miss\" };
+            try { t[{}] } catch err { h = err[\"kind\"] };
+            try { t[nil] = 1 } catch err { j = err[\"kind\"] }");
+        assert_eq!(global(&vm, "a"), SunValue::from(2));
+        assert_eq!(global(&vm, "b"), SunValue::from("yes"));
+        assert_eq!(global(&vm, "c"), SunValue::from("ten"));
+        assert_eq!(global(&vm, "d"), SunValue::from("table"));
+        assert_eq!(global(&vm, "e"), SunValue::from(2));
+        assert_eq!(global(&vm, "f"), SunValue::from(2));
+        assert_eq!(global(&vm, "g"), SunValue::from("hit"));
+        assert_eq!(global(&vm, "h"), SunValue::from("KeyError"));
+        assert_eq!(global(&vm, "j"), SunValue::from("KeyError"));
+    }
+
+    #[test]
+    fn test_table_literal_key() {
+        let vm = run("k = {}; n = nil;
+            t = {1, \"a\": 2, 2: \"two\", T: \"yes\", (1 + 2): \"three\", (k): \"table\"};
+            a = t[0]; b = t[\"a\"]; c = t[2]; d = t[T]; e = t[3]; f = t[k];
+            try { u = {(n): 1} } catch err { h = err[\"kind\"] }");
+        assert_eq!(global(&vm, "a"), SunValue::from(1));
+        assert_eq!(global(&vm, "b"), SunValue::from(2));
+        assert_eq!(global(&vm, "c"), SunValue::from("two"));
+        assert_eq!(global(&vm, "d"), SunValue::from("yes"));
+        assert_eq!(global(&vm, "e"), SunValue::from("three"));
+        assert_eq!(global(&vm, "f"), SunValue::from("table"));
+        assert_eq!(global(&vm, "h"), SunValue::from("KeyError"));
+    }
+
+    #[test]
+    fn test_class() {
+        let vm = run("class Shape {
+                fn new(name) { self.name = name }
+                fn describe(self) { self.name + \": \" + self.area() }
+                fn area(self) { 0 }
+            };
+            class Rect : Shape {
+                fn new(w, h) { self.name = \"rect\"; self.w = w; self.h = h };
+                fn area(self) { self.w * self.h }
+                fn grow(self, n) { self.w += n; self[\"h\"] += n }
+            };
+            class Empty {};
+            r = Rect(2, 3); q = r; q.grow(1);
+            a = r.area(); w = r.w; h = r[\"h\"]; d = r.describe();
+            sh = Shape(\"dot\"); s = sh.describe(); e = Empty();
+            try { e.missing } catch err { k1 = err[\"kind\"] };
+            try { class Table {} } catch err { k2 = err[\"kind\"] };
+            try { class Bad : Number {} } catch err { k3 = err[\"kind\"] }");
+        assert_eq!(global(&vm, "a"), SunValue::from(12));
+        assert_eq!(global(&vm, "w"), SunValue::from(3));
+        assert_eq!(global(&vm, "h"), SunValue::from(4));
+        assert_eq!(global(&vm, "d"), SunValue::from("rect: 12"));
+        assert_eq!(global(&vm, "s"), SunValue::from("dot: 0"));
+        assert_eq!(global(&vm, "e").get_name(), "Empty");
+        assert_eq!(global(&vm, "k1"), SunValue::from("AttributeError"));
+        assert_eq!(global(&vm, "k2"), SunValue::from("TypeError"));
+        assert_eq!(global(&vm, "k3"), SunValue::from("TypeError"));
+    }
+
+    #[test]
+    fn test_call_chain() {
+        let vm = run(
+            "class B { fn new() { self.v = 7 } fn get(self) { self.v } };
+            fn f() { {10, \"x\": 20} };
+            fn adder(n) { fn(m) { n + m } };
+            a = B().get(); b = f().x; c = f()[0]; d = adder(1)(2); e = B().v;
+            g = {f}[0]()[\"x\"]",
+        );
+        assert_eq!(global(&vm, "a"), SunValue::from(7));
+        assert_eq!(global(&vm, "b"), SunValue::from(20));
+        assert_eq!(global(&vm, "c"), SunValue::from(10));
+        assert_eq!(global(&vm, "d"), SunValue::from(3));
+        assert_eq!(global(&vm, "e"), SunValue::from(7));
+        assert_eq!(global(&vm, "g"), SunValue::from(20));
+    }
+
+    #[test]
+    fn test_operator_overload() {
+        let vm = run("class Money {
+                fn new(cents) { self.cents = cents }
+                fn add(self, other) { Money(self.cents + other.cents) }
+                fn neg(self) { Money(-self.cents) }
+                fn eq(self, other) { self.cents == other.cents }
+                fn less(self, other) { self.cents < other.cents }
+                fn index(self, k) { k == \"euro\" ? self.cents / 100 : self.cents }
+                fn call(self, n) { self.cents * n }
+            };
+            class Tag {};
+            class Score { fn new(v) { self.v = v } fn less(self, n) { self.v < n } };
+            s = Score(10); sg = s > 5; sge = s >= 10; sle = s <= 5; sl = s < 20;
+            a = Money(150); b = Money(250); c = a + b; n = -a;
+            total = c.cents; neg = n.cents; same = c == Money(400);
+            diff = a != b; lt = a < b; gt = a > b; le = a <= Money(150); ge = a >= b;
+            euro = c[\"euro\"]; tripled = a(3);
+            t = Tag(); u = t; id = t == u; other = t == Tag();
+            try { t(1) } catch err { k = err[\"kind\"] }");
+        assert_eq!(global(&vm, "total"), SunValue::from(400));
+        assert_eq!(global(&vm, "neg"), SunValue::from(-150));
+        assert_eq!(global(&vm, "same"), SunValue::from(true));
+        assert_eq!(global(&vm, "diff"), SunValue::from(true));
+        assert_eq!(global(&vm, "lt"), SunValue::from(true));
+        assert_eq!(global(&vm, "gt"), SunValue::from(false));
+        assert_eq!(global(&vm, "le"), SunValue::from(true));
+        assert_eq!(global(&vm, "ge"), SunValue::from(false));
+        assert_eq!(global(&vm, "sg"), SunValue::from(true));
+        assert_eq!(global(&vm, "sge"), SunValue::from(true));
+        assert_eq!(global(&vm, "sle"), SunValue::from(false));
+        assert_eq!(global(&vm, "sl"), SunValue::from(true));
+        assert_eq!(global(&vm, "euro"), SunValue::from(4));
+        assert_eq!(global(&vm, "tripled"), SunValue::from(450));
+        assert_eq!(global(&vm, "id"), SunValue::from(true));
+        assert_eq!(global(&vm, "other"), SunValue::from(false));
+        assert_eq!(global(&vm, "k"), SunValue::from("CallError"));
+    }
+
+    #[test]
+    fn test_import() {
+        let dir = std::env::temp_dir().join(format!("sun_import_{}", std::process::id()));
+        let files = [
+            (
+                "utils/counter.sun",
+                "count = 0; fn next() { count += 1; count }",
+            ),
+            (
+                "utils/strings.sun",
+                "@\"counter.sun\"; greeting = \"hi\"; fn shout(s) { s + \"!\" };
+                fn greet(name) { shout(greeting + \" \" + name) }; fn bump() { counter.next() }",
+            ),
+            ("lib/extra.sun", "value = 42"),
+            (
+                "shapes.sun",
+                "class P { fn new(x) { self.x = x } fn kind(self) { \"module\" } };
+                class Q : P { fn new() { self.x = 0 } }; fn make() { Q() }",
+            ),
+            ("a.sun", "@\"b.sun\""),
+            ("b.sun", "@\"a.sun\""),
+        ];
+        for (name, src) in files {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, src).unwrap();
+        }
+        let src = "@\"utils/strings.sun\"; s = strings.greet(\"sun\"); g = strings.greeting;
+            again = @\"utils/strings\"; c1 = again.bump(); c2 = strings.bump();
+            greeting = \"main\"; s2 = strings.greet(\"x\"); extra = @\"extra.sun\"; v = extra.value;
+            try { @\"a.sun\" } catch err { k1 = err[\"kind\"]; m = err[\"message\"] };
+            try { @\"missing.sun\" } catch err { k2 = err[\"kind\"] };
+            class P { fn new() { } fn kind(self) { \"main\" } };
+            @\"shapes\"; q = shapes.make(); p = P(); mk = q.kind(); pk = p.kind()";
+        let main = dir.join("main.sun");
+        let mut vm = VirtualMachine::new(false, false, false);
+        vm.set_search_paths(vec![dir.join("lib")]);
+        let proto =
+            ParseProto::new(src.as_bytes(), &main.to_string_lossy(), false, false, false).unwrap();
+        let res = vm.run(&proto);
+        std::fs::remove_dir_all(&dir).unwrap();
+        res.unwrap();
+        assert_eq!(global(&vm, "s"), SunValue::from("hi sun!"));
+        assert_eq!(global(&vm, "g"), SunValue::from("hi"));
+        assert_eq!(global(&vm, "c1"), SunValue::from(1));
+        assert_eq!(global(&vm, "c2"), SunValue::from(2));
+        assert_eq!(global(&vm, "s2"), SunValue::from("hi x!"));
+        assert_eq!(global(&vm, "v"), SunValue::from(42));
+        assert_eq!(global(&vm, "k1"), SunValue::from("ImportError"));
+        assert!(global(&vm, "m").to_string().starts_with("circular import"));
+        assert_eq!(global(&vm, "k2"), SunValue::from("ImportError"));
+        assert!(!vm.value_map.contains_key("count"));
+        assert_eq!(global(&vm, "mk"), SunValue::from("module"));
+        assert_eq!(global(&vm, "pk"), SunValue::from("main"));
+    }
+
+    /// 测试用的原生库，导出 `Counter` 类型和它的构造函数
+    struct CounterLib;
+
+    impl ImportAble for CounterLib {
+        fn export_lib() -> ExportLib {
+            fn new(args: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
+                let attributes = HashMap::from([("n".to_string(), args[0].clone())]);
+                let counter = SunValue::from(Class::new("Counter", attributes));
+                Ok(vec![SunPointer::new(counter)])
+            }
+            fn double(args: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
+                let n = match args[0].get() {
+                    SunValue::Class(c) => c.get_attribute("n").map(|n| n.get()),
+                    _ => None,
+                };
+                match n {
+                    Some(SunValue::Integer(n)) => Ok(vec![SunPointer::new(SunValue::from(n * 2))]),
+                    _ => Err(SunError::ParaError("invalid counter".to_string())),
+                }
+            }
+            let mut meta = SunMeta::new("Counter", SunBase::Other("Class".to_string()));
+            meta.set_method("double", Function::from(double as RustFunction));
+            let new = SunPointer::new(SunValue::from(new as RustFunction));
+            ExportLib::new(
+                HashMap::from([("Counter".to_string(), meta)]),
+                HashMap::from([("new".to_string(), new)]),
+            )
+        }
+    }
+
+    #[test]
+    fn test_native_lib() {
+        let mut vm = VirtualMachine::new(false, false, false);
+        vm.register_lib::<CounterLib>("counter");
+        vm.register_lib::<CounterLib>("counter_again");
+        vm.register_lib_with("counter_other", CounterLib::export_lib);
+        let src = "@\"math\"; r = math.sqrt(16); m = math.max(3, 7.5, -1); f = math.floor(2.5);
+            p = math.pi > 3; again = @\"math\"; again.tau = 6; same = math.tau;
+            @\"counter\"; c = counter.new(21); d = c.double(); n = c.n;
+            counter.tag = 1; a = @\"counter_again\"; tag = a.tag;
+            try { @\"counter_other\" } catch err { k = err[\"kind\"] }";
+        let proto = ParseProto::new(src.as_bytes(), "<test>", false, false, false).unwrap();
+        vm.run(&proto).unwrap();
+        assert_eq!(global(&vm, "r"), SunValue::from(4.0));
+        assert_eq!(global(&vm, "m"), SunValue::from(7.5));
+        assert_eq!(global(&vm, "f"), SunValue::from(2.0));
+        assert_eq!(global(&vm, "p"), SunValue::from(true));
+        assert_eq!(global(&vm, "same"), SunValue::from(6));
+        assert_eq!(global(&vm, "d"), SunValue::from(42));
+        assert_eq!(global(&vm, "n"), SunValue::from(21));
+        assert_eq!(global(&vm, "tag"), SunValue::from(1));
+        assert_eq!(global(&vm, "k"), SunValue::from("ImportError"));
+    }
+
+    #[test]
+    fn test_user_data() {
+        struct Account {
+            balance: i64,
+        }
+        fn deposit(args: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
+            match (args[0].get(), args[1].get()) {
+                (SunValue::UserData(u), SunValue::Integer(n)) => {
+                    u.borrow_mut::<Account>()?.balance += n;
+                    Ok(vec![])
+                }
+                _ => Err(SunError::ParaError("invalid deposit".to_string())),
+            }
+        }
+        fn balance(args: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
+            match args[0].get() {
+                SunValue::UserData(u) => {
+                    let n = u.borrow::<Account>()?.balance;
+                    Ok(vec![SunPointer::new(SunValue::from(n))])
+                }
+                _ => Err(SunError::ParaError("invalid balance".to_string())),
+            }
+        }
+        let ticks = Rc::new(Cell::new(0));
+        let account = UserData::new("Account", Account { balance: 0 });
+        let mut vm = VirtualMachine::new(false, false, false);
+        let (t, a) = (ticks.clone(), account.clone());
+        vm.register_lib_with("app", move || {
+            let t = t.clone();
+            let tick = Function::native(move |_| {
+                t.set(t.get() + 1);
+                Ok(vec![SunPointer::new(SunValue::from(t.get()))])
+            });
+            let mut meta = SunMeta::new("Account", SunBase::Object);
+            meta.set_method("deposit", Function::from(deposit as RustFunction));
+            meta.set_method("balance", Function::from(balance as RustFunction));
+            ExportLib::new(
+                HashMap::from([("Account".to_string(), meta)]),
+                HashMap::from([
+                    ("tick".to_string(), SunPointer::new(SunValue::from(tick))),
+                    (
+                        "account".to_string(),
+                        SunPointer::new(SunValue::from(a.clone())),
+                    ),
+                ]),
+            )
+        });
+        let src = "@\"app\"; app.tick(); app.tick(); n = app.tick();
+            acc = app.account; acc.deposit(30); b = acc.balance();
+            try { acc.withdraw(1) } catch err { k = err[\"kind\"] }";
+        let proto = ParseProto::new(src.as_bytes(), "<test>", false, false, false).unwrap();
+        vm.run(&proto).unwrap();
+        assert_eq!(global(&vm, "n"), SunValue::from(3));
+        assert_eq!(global(&vm, "b"), SunValue::from(30));
+        assert_eq!(global(&vm, "k"), SunValue::from("AttributeError"));
+        assert_eq!(ticks.get(), 3);
+        assert_eq!(account.borrow::<Account>().unwrap().balance, 30);
+        assert!(matches!(
+            account.borrow::<String>(),
+            Err(SunError::TypeError(_))
+        ));
+        // 宿主程序持有可变借用时，脚本中的借用抛出可以捕获的 `RunError`
+        let guard = account.borrow_mut::<Account>().unwrap();
+        let src = "try { acc.balance() } catch err { j = err[\"kind\"] }";
+        let proto = ParseProto::new(src.as_bytes(), "<test>", false, false, false).unwrap();
+        vm.run(&proto).unwrap();
+        drop(guard);
+        assert_eq!(global(&vm, "j"), SunValue::from("RunError"));
+    }
+}
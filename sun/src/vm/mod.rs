@@ -1,2 +1,5 @@
-pub mod command;
+pub mod frame;
 pub mod machine;
+pub mod module;
+
+pub use sun_core::utils::command;
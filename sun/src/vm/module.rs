@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use sun_core::utils::{SunError, SunPointer};
+
+/// 已导入的模块
+#[derive(Debug)]
+pub struct Module {
+    /// 模块的全局变量表，执行模块中的代码时移入虚拟机
+    pub globals: HashMap<String, SunPointer>,
+    /// 模块顶层变量组成的 `Table`，导入完成前为 `None`
+    pub namespace: Option<SunPointer>,
+}
+
+impl Module {
+    /// 创建新的模块 `globals`: 初始的全局变量表
+    pub fn new(globals: HashMap<String, SunPointer>) -> Self {
+        Module {
+            globals,
+            namespace: None,
+        }
+    }
+}
+
+/// 模块的查找目录：宿主程序设置的目录，未设置时为环境变量 `SUN_PATH` 中的目录
+/// `configured`: 宿主程序设置的目录 `env`: 环境变量 `SUN_PATH` 的值
+pub fn search_paths(configured: Option<&[PathBuf]>, env: Option<OsString>) -> Vec<PathBuf> {
+    match configured {
+        Some(paths) => paths.to_vec(),
+        None => env
+            .map(|paths| env::split_paths(&paths).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// 查找模块文件，相对路径先从 `importer` 所在的目录查找，再依次查找 `search` 中的目录，
+/// 没有扩展名时添加 `.sun`，返回模块文件的绝对路径
+pub fn resolve(path: &str, importer: &str, search: &[PathBuf]) -> Result<PathBuf, SunError> {
+    let mut target = PathBuf::from(path);
+    if target.extension().is_none() {
+        target.set_extension("sun");
+    }
+    let mut dirs = Vec::new();
+    if target.is_relative() {
+        let dir = Path::new(importer).parent().unwrap_or(Path::new(""));
+        dirs.push(dir.to_path_buf());
+        dirs.extend(search.iter().cloned());
+    } else {
+        dirs.push(PathBuf::new());
+    }
+    dirs.iter()
+        .map(|dir| dir.join(&target))
+        .find(|file| file.is_file())
+        .and_then(|file| file.canonicalize().ok())
+        .ok_or_else(|| SunError::ImportError(format!("failed to find module `{path}`")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_paths() {
+        let (a, b) = (PathBuf::from("a"), PathBuf::from("b"));
+        let joined = env::join_paths([&a, &b]).unwrap();
+        let configured = [PathBuf::from("c")];
+        assert_eq!(search_paths(None, Some(joined.clone())), [a, b]);
+        assert_eq!(search_paths(Some(&configured), Some(joined)), configured);
+        assert!(search_paths(None, None).is_empty());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = env::temp_dir().join(format!("sun_resolve_{}", std::process::id()));
+        let lib = dir.join("lib");
+        std::fs::create_dir_all(&lib).unwrap();
+        std::fs::write(dir.join("local.sun"), "").unwrap();
+        std::fs::write(lib.join("extra.sun"), "").unwrap();
+        let importer = dir.join("main.sun").to_string_lossy().to_string();
+        let local = resolve("local", &importer, &[]);
+        let extra = resolve("extra.sun", &importer, &[lib]);
+        let missing = resolve("extra", &importer, &[]);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(local.unwrap().ends_with("local.sun"));
+        assert!(extra.unwrap().ends_with("lib/extra.sun"));
+        assert_eq!(missing.unwrap_err().kind(), "ImportError");
+    }
+}
@@ -50,7 +50,7 @@ impl fmt::Display for Class {
         let str = format!("{}:", self.name).color(Colors::YellowFg);
         writeln!(f, "{}", str)?;
         for (key, value) in self.attributes.iter() {
-            write!(f, "{:<10} {:<10?}\n", key, value)?;
+            writeln!(f, "{:<10} {:<10?}", key, value)?;
         }
         Ok(())
     }
@@ -62,7 +62,7 @@ impl fmt::Debug for Class {
         let str = format!("{}:", self.name).color(Colors::YellowFg);
         writeln!(f, "{}:", str)?;
         for (key, value) in self.attributes.iter() {
-            write!(f, "{:<10} {:<10?}\n", key, value)?;
+            writeln!(f, "{:<10} {:<10?}", key, value)?;
         }
         Ok(())
     }
@@ -0,0 +1,48 @@
+use crate::{container::UserFunction, utils::SunPointer};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+/// 变量的存储单元，闭包与定义它的调用帧共享同一个存储单元
+pub type Upvalue = Rc<RefCell<SunPointer>>;
+
+/// 闭包捕获变量的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capture {
+    /// 外层函数的局部变量槽位
+    Local(usize),
+    /// 外层函数捕获的变量序号
+    Upvalue(usize),
+}
+
+/// Closure 的数据，由脚本函数和它捕获的变量组成
+#[derive(Clone)]
+pub struct Closure {
+    /// 脚本函数
+    function: UserFunction,
+    /// 捕获的变量
+    upvalues: Vec<Upvalue>,
+}
+
+impl Closure {
+    /// 创建新的闭包
+    pub fn new(function: UserFunction, upvalues: Vec<Upvalue>) -> Self {
+        Closure { function, upvalues }
+    }
+
+    /// 获取脚本函数
+    pub fn get_function(&self) -> &UserFunction {
+        &self.function
+    }
+
+    /// 获取捕获的变量，引用计数增加
+    pub fn get_upvalues(&self) -> Vec<Upvalue> {
+        self.upvalues.clone()
+    }
+}
+
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "closure {:?}", self.function)
+    }
+}
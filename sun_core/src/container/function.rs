@@ -1,11 +1,18 @@
-use crate::utils::{IsMachine, SunPointer};
+use crate::{
+    container::Closure,
+    utils::{Command, IsMachine, Span, SunError, SunPointer},
+};
 use std::fmt;
+use std::rc::Rc;
 
 /// Function 的数据
 #[derive(Clone)]
 pub enum Function {
     RustFunction(RustFunction),
+    NativeFunction(NativeFunction),
     SysFunction(SysFunction),
+    UserFunction(UserFunction),
+    Closure(Closure),
 }
 
 impl From<RustFunction> for Function {
@@ -14,26 +21,125 @@ impl From<RustFunction> for Function {
     }
 }
 
+impl From<NativeFunction> for Function {
+    fn from(value: NativeFunction) -> Self {
+        Function::NativeFunction(value)
+    }
+}
+
 impl From<SysFunction> for Function {
     fn from(value: SysFunction) -> Self {
         Function::SysFunction(value)
     }
 }
 
+impl From<UserFunction> for Function {
+    fn from(value: UserFunction) -> Self {
+        Function::UserFunction(value)
+    }
+}
+
+impl From<Closure> for Function {
+    fn from(value: Closure) -> Self {
+        Function::Closure(value)
+    }
+}
+
+impl Function {
+    /// 由闭包创建原生函数，闭包可以捕获宿主程序的状态
+    pub fn native(
+        f: impl Fn(Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> + 'static,
+    ) -> Self {
+        Function::NativeFunction(Rc::new(f))
+    }
+}
+
 impl fmt::Display for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "function")
+        match self {
+            Function::UserFunction(uf) => write!(f, "function {}", uf.get_name()),
+            Function::Closure(c) => write!(f, "function {}", c.get_function().get_name()),
+            _ => write!(f, "function"),
+        }
     }
 }
 
 impl fmt::Debug for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "function")
+        match self {
+            Function::UserFunction(uf) => write!(f, "{:?}", uf),
+            Function::Closure(c) => write!(f, "{:?}", c),
+            _ => write!(f, "function"),
+        }
     }
 }
 
 /// RustFunction 的数据
-pub type RustFunction = fn(Vec<SunPointer>) -> Vec<SunPointer>;
+pub type RustFunction = fn(Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError>;
+
+/// NativeFunction 的数据，可以捕获状态的 RustFunction，需要修改的状态使用 `Cell` 或 `RefCell` 保存
+pub type NativeFunction = Rc<dyn Fn(Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError>>;
 
 /// SysFunction 的数据
-pub type SysFunction = fn(&mut dyn IsMachine);
+pub type SysFunction = fn(&mut dyn IsMachine) -> Result<(), SunError>;
+
+/// UserFunction 的数据，由脚本中的 `fn` 语句定义
+#[derive(Clone)]
+pub struct UserFunction {
+    /// 函数名
+    name: String,
+    /// 形参名
+    params: Vec<String>,
+    /// 函数体的指令序列，每条指令附带源码位置
+    body: Rc<Vec<(Command, Span)>>,
+    /// 调用帧的大小，即局部变量槽位的数量
+    frame_size: usize,
+}
+
+impl UserFunction {
+    /// 创建新的脚本函数
+    pub fn new(
+        name: &str,
+        params: Vec<String>,
+        body: Vec<(Command, Span)>,
+        frame_size: usize,
+    ) -> Self {
+        UserFunction {
+            name: name.to_string(),
+            params,
+            body: Rc::new(body),
+            frame_size,
+        }
+    }
+
+    /// 获取函数名
+    pub fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// 获取形参名
+    pub fn get_params(&self) -> &[String] {
+        &self.params
+    }
+
+    /// 获取函数体的指令序列，引用计数增加
+    pub fn get_body(&self) -> Rc<Vec<(Command, Span)>> {
+        Rc::clone(&self.body)
+    }
+
+    /// 获取调用帧的大小
+    pub fn get_frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    /// 获取定义函数的源文件名，函数体为空时返回 `None`
+    pub fn get_module(&self) -> Option<&Rc<str>> {
+        self.body.first().map(|(_, span)| &span.file)
+    }
+}
+
+impl fmt::Debug for UserFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "function {}({})", self.name, self.params.join(", "))
+    }
+}
@@ -1,9 +1,15 @@
 pub mod class;
+pub mod closure;
 pub mod function;
 pub mod table;
+pub mod table_key;
+pub mod user_data;
 pub mod value;
 
 pub use class::{Class, IsSunClass};
-pub use function::{Function, RustFunction, SysFunction};
-pub use table::Table;
+pub use closure::{Capture, Closure, Upvalue};
+pub use function::{Function, NativeFunction, RustFunction, SysFunction, UserFunction};
+pub use table::{Table, TableIter};
+pub use table_key::TableKey;
+pub use user_data::UserData;
 pub use value::SunValue;
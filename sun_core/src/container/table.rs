@@ -1,4 +1,7 @@
-use crate::{container::SunValue, utils::SunPointer};
+use crate::{
+    container::{SunValue, TableKey},
+    utils::{SunError, SunPointer},
+};
 use colorized::*;
 use std::collections::HashMap;
 use std::fmt;
@@ -7,7 +10,7 @@ use std::fmt;
 #[derive(Clone)]
 pub struct Table {
     array: Vec<SunPointer>,
-    dict: HashMap<String, SunPointer>,
+    dict: HashMap<TableKey, SunPointer>,
 }
 
 impl Table {
@@ -31,22 +34,56 @@ impl Table {
 
     /// 向字典添加新键值对
     pub fn append_kv(&mut self, key: String, value: SunValue) {
-        self.dict.insert(key, SunPointer::new(value));
+        self.dict
+            .insert(TableKey::from(key), SunPointer::new(value));
     }
 
     /// 按索引获取内容的指针，引用计数增加
     pub fn get_by_idx(&self, idx: usize) -> Option<SunPointer> {
-        match self.array.get(idx) {
-            Some(value) => Some(value.clone()),
-            None => None,
-        }
+        self.array.get(idx).cloned()
+    }
+
+    /// 获取数组部分的所有指针
+    pub fn array(&self) -> &[SunPointer] {
+        &self.array
     }
 
     /// 按键获取内容的指针，引用计数增加
     pub fn get_by_key(&self, key: &str) -> Option<SunPointer> {
-        match self.dict.get(key) {
-            Some(value) => Some(value.clone()),
-            None => None,
+        self.dict.get(&TableKey::from(key)).cloned()
+    }
+
+    /// 数组范围内的整数键对应的索引
+    fn array_index(&self, key: &TableKey) -> Option<usize> {
+        match key {
+            TableKey::Integer(n) => usize::try_from(*n).ok().filter(|&i| i < self.array.len()),
+            _ => None,
+        }
+    }
+
+    /// 按键获取内容的指针，数组范围内的整数为数组的索引，其他的键从字典中获取
+    pub fn get(&self, key: &TableKey) -> Option<SunPointer> {
+        match self.array_index(key) {
+            Some(idx) => self.get_by_idx(idx),
+            None => self.dict.get(key).cloned(),
+        }
+    }
+
+    /// 按键设置内容的指针，数组范围内的整数为数组的索引，其他的键写入字典
+    pub fn set(&mut self, key: TableKey, value: SunPointer) {
+        match self.array_index(&key) {
+            Some(idx) => self.array[idx] = value,
+            None => {
+                self.dict.insert(key, value);
+            }
+        }
+    }
+
+    /// 按键移除内容的指针，数组范围内的整数为数组的索引，其他的键从字典中移除
+    pub fn remove(&mut self, key: &TableKey) -> Option<SunPointer> {
+        match self.array_index(key) {
+            Some(idx) => Some(self.array.remove(idx)),
+            None => self.dict.remove(key),
         }
     }
 
@@ -61,7 +98,7 @@ impl Table {
 
     /// 按键移除内容的指针
     pub fn remove_by_key(&mut self, key: &str) -> Option<SunPointer> {
-        self.dict.remove(key)
+        self.dict.remove(&TableKey::from(key))
     }
 
     /// 合并两个 `Table` 的字典
@@ -77,22 +114,43 @@ impl Table {
     /// 获取数组长度
     pub fn alen(&self) -> SunPointer {
         let n = self.array.len();
-        SunPointer::new(SunValue::from(n as f64))
+        SunPointer::new(SunValue::from(n as i64))
     }
 
     /// 获取字典长度
     pub fn dlen(&self) -> SunPointer {
         let n = self.dict.len();
-        SunPointer::new(SunValue::from(n as f64))
+        SunPointer::new(SunValue::from(n as i64))
     }
 
     /// 获取总长度
     pub fn len(&self) -> SunPointer {
         let n = self.dict.len() + self.array.len();
-        SunPointer::new(SunValue::from(n as f64))
+        SunPointer::new(SunValue::from(n as i64))
     }
 
-    /// 自身的深拷贝
+    /// 字典的键值对，按键排序，每个键值对是数组为 `{key, value}` 的 `Table`
+    pub fn pairs(&self) -> Table {
+        let mut keys: Vec<&TableKey> = self.dict.keys().collect();
+        keys.sort();
+        let array = keys
+            .into_iter()
+            .map(|key| {
+                let pair = Table {
+                    array: vec![key.to_pointer(), self.dict[key].clone()],
+                    dict: HashMap::new(),
+                };
+                SunPointer::new(SunValue::from(pair))
+            })
+            .collect();
+        Table {
+            array,
+            dict: HashMap::new(),
+        }
+    }
+
+    /// 自身的深拷贝，`Table` 键按地址计算哈希，不受内部可变性影响
+    #[allow(clippy::mutable_key_type)]
     pub fn deep_copy(&self) -> Self {
         let array = self.array.iter().map(|p| p.deep_copy()).collect();
         let dict = self
@@ -104,6 +162,45 @@ impl Table {
     }
 }
 
+/// `Table` 数组部分的迭代器，每次迭代时重新读取 `Table`，迭代期间对数组的修改对迭代器可见
+#[derive(Debug)]
+pub struct TableIter {
+    /// 被迭代的 `Table` 的指针
+    table: SunPointer,
+    /// 下一个值的索引
+    index: usize,
+}
+
+impl TableIter {
+    /// 创建 `Table` 的迭代器，`table` 不是 `Table` 时返回 `None`
+    pub fn new(table: SunPointer) -> Option<Self> {
+        match *table.borrow() {
+            SunValue::Table(_) => (),
+            _ => return None,
+        }
+        Some(TableIter { table, index: 0 })
+    }
+}
+
+impl Iterator for TableIter {
+    type Item = SunPointer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = match &*self.table.borrow() {
+            SunValue::Table(t) => t.get_by_idx(self.index),
+            _ => None,
+        };
+        self.index += 1;
+        item
+    }
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl fmt::Display for Table {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f)?;
@@ -113,9 +210,9 @@ impl fmt::Display for Table {
             "Array".color(Colors::YellowFg),
             self.array.len()
         )?;
-        write!(f, "{:<10} {:<10}\n", "Index", "Value")?;
+        writeln!(f, "{:<10} {:<10}", "Index", "Value")?;
         for (i, item) in self.array.iter().enumerate() {
-            write!(f, "{:<10} {:<10?}\n", i, item)?;
+            writeln!(f, "{:<10} {:<10?}", i, item)?;
         }
         writeln!(
             f,
@@ -123,9 +220,9 @@ impl fmt::Display for Table {
             "Dict".color(Colors::YellowFg),
             self.array.len()
         )?;
-        write!(f, "{:<10} {:<10}\n", "Key", "Value")?;
+        writeln!(f, "{:<10} {:<10}", "Key", "Value")?;
         for (key, value) in self.dict.iter() {
-            write!(f, "{:<10} {:<10?}\n", key, value)?;
+            writeln!(f, "{:<10} {:<10?}", key, value)?;
         }
 
         Ok(())
@@ -136,15 +233,15 @@ impl fmt::Debug for Table {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f)?;
         writeln!(f, "Array:")?;
-        write!(f, "{:<10} {:<10}\n", "Index", "Value")?;
+        writeln!(f, "{:<10} {:<10}", "Index", "Value")?;
         for (i, item) in self.array.iter().enumerate() {
-            write!(f, "{:<10} {:<10?}\n", i, item)?;
+            writeln!(f, "{:<10} {:<10?}", i, item)?;
         }
 
         writeln!(f, "Dict:")?;
-        write!(f, "{:<10} {:<10}\n", "Key", "Value")?;
+        writeln!(f, "{:<10} {:<10}", "Key", "Value")?;
         for (key, value) in self.dict.iter() {
-            write!(f, "{:<15} {:<10?}\n", key, value)?;
+            writeln!(f, "{:<15} {:<10?}", key, value)?;
         }
 
         Ok(())
@@ -179,6 +276,16 @@ impl PartialEq for Table {
 
 impl Eq for Table {}
 
+impl From<SunError> for Table {
+    /// 被捕获的错误，`kind` 为错误类型名，`message` 为错误信息
+    fn from(value: SunError) -> Self {
+        let mut table = Table::new();
+        table.append_kv("kind".to_string(), SunValue::from(value.kind()));
+        table.append_kv("message".to_string(), SunValue::from(value.message()));
+        table
+    }
+}
+
 // impl From<TableC> for Table {
 //     fn from(value: TableC) -> Self {
 //         let mut array = Vec::new();
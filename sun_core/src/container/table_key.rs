@@ -0,0 +1,125 @@
+use crate::{container::SunValue, utils::SunPointer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+/// `Table` 字典的键，`Table` 按引用区分
+#[derive(Clone, Debug)]
+pub enum TableKey {
+    Boolean(bool),
+    Integer(i64),
+    String(Vec<u8>),
+    Table(SunPointer),
+}
+
+impl TableKey {
+    /// 获取值对应的键，值为整数的 `Float` 与 `Integer` 相等，`Nil` 等其他值不能作为键
+    pub fn new(value: &SunPointer) -> Option<Self> {
+        match &*value.borrow() {
+            SunValue::Table(_) => Some(TableKey::Table(value.clone())),
+            other => Self::from_value(other),
+        }
+    }
+
+    /// 获取按值比较的键，`Table` 没有对应的指针所以返回 `None`
+    pub fn from_value(value: &SunValue) -> Option<Self> {
+        match value {
+            SunValue::Boolean(b) => Some(TableKey::Boolean(*b)),
+            SunValue::Integer(n) => Some(TableKey::Integer(*n)),
+            SunValue::Float(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => {
+                Some(TableKey::Integer(*n as i64))
+            }
+            SunValue::String(s) => Some(TableKey::String(s.clone())),
+            _ => None,
+        }
+    }
+
+    /// 键对应的值，`Table` 返回原来的指针
+    pub fn to_pointer(&self) -> SunPointer {
+        match self {
+            TableKey::Boolean(b) => SunPointer::new(SunValue::from(*b)),
+            TableKey::Integer(n) => SunPointer::new(SunValue::from(*n)),
+            TableKey::String(s) => SunPointer::new(SunValue::from(s.clone())),
+            TableKey::Table(p) => p.clone(),
+        }
+    }
+
+    /// 排序时类型的顺序
+    fn rank(&self) -> u8 {
+        match self {
+            TableKey::Boolean(_) => 0,
+            TableKey::Integer(_) => 1,
+            TableKey::String(_) => 2,
+            TableKey::Table(_) => 3,
+        }
+    }
+}
+
+impl From<&str> for TableKey {
+    fn from(value: &str) -> Self {
+        TableKey::String(value.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for TableKey {
+    fn from(value: String) -> Self {
+        TableKey::String(value.into_bytes())
+    }
+}
+
+impl PartialEq for TableKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (TableKey::Boolean(b1), TableKey::Boolean(b2)) => b1 == b2,
+            (TableKey::Integer(n1), TableKey::Integer(n2)) => n1 == n2,
+            (TableKey::String(s1), TableKey::String(s2)) => s1 == s2,
+            (TableKey::Table(p1), TableKey::Table(p2)) => Rc::ptr_eq(p1, p2),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for TableKey {}
+
+impl Hash for TableKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            TableKey::Boolean(b) => b.hash(state),
+            TableKey::Integer(n) => n.hash(state),
+            TableKey::String(s) => s.hash(state),
+            TableKey::Table(p) => Rc::as_ptr(p).hash(state),
+        }
+    }
+}
+
+impl PartialOrd for TableKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TableKey {
+    /// 先按类型排序，`Table` 之间按地址排序
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (TableKey::Boolean(b1), TableKey::Boolean(b2)) => b1.cmp(b2),
+            (TableKey::Integer(n1), TableKey::Integer(n2)) => n1.cmp(n2),
+            (TableKey::String(s1), TableKey::String(s2)) => s1.cmp(s2),
+            (TableKey::Table(p1), TableKey::Table(p2)) => Rc::as_ptr(p1).cmp(&Rc::as_ptr(p2)),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl fmt::Display for TableKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableKey::Boolean(b) => f.pad(&b.to_string()),
+            TableKey::Integer(n) => f.pad(&n.to_string()),
+            TableKey::String(s) => f.pad(&String::from_utf8_lossy(s)),
+            TableKey::Table(p) => f.pad(&format!("<table: {:p}>", Rc::as_ptr(p))),
+        }
+    }
+}
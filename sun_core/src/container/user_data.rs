@@ -0,0 +1,69 @@
+use crate::utils::err::SunError;
+use std::any::{type_name, Any};
+use std::cell::{Ref, RefCell, RefMut};
+use std::fmt;
+use std::rc::Rc;
+
+/// 宿主程序的对象，类型方法由同名的元数据提供，拷贝时共享同一个对象
+#[derive(Clone)]
+pub struct UserData {
+    /// 元数据的类型名
+    name: String,
+    data: Rc<RefCell<Box<dyn Any>>>,
+}
+
+impl UserData {
+    /// 包装宿主对象 `name`: 元数据的类型名 `value`: 宿主对象
+    pub fn new<T: Any>(name: &str, value: T) -> Self {
+        UserData {
+            name: name.to_string(),
+            data: Rc::new(RefCell::new(Box::new(value))),
+        }
+    }
+
+    /// 获取类型名
+    pub fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// 以 `T` 类型借用宿主对象
+    /// 对象正在被可变借用时返回 `RunError`，类型不符时返回 `TypeError`
+    pub fn borrow<T: Any>(&self) -> Result<Ref<'_, T>, SunError> {
+        let data = self.data.try_borrow().map_err(|_| self.borrowed())?;
+        Ref::filter_map(data, |data| data.downcast_ref::<T>()).map_err(|_| self.mismatch::<T>())
+    }
+
+    /// 以 `T` 类型可变借用宿主对象
+    /// 对象正在被借用时返回 `RunError`，类型不符时返回 `TypeError`
+    pub fn borrow_mut<T: Any>(&self) -> Result<RefMut<'_, T>, SunError> {
+        let data = self.data.try_borrow_mut().map_err(|_| self.borrowed())?;
+        RefMut::filter_map(data, |data| data.downcast_mut::<T>()).map_err(|_| self.mismatch::<T>())
+    }
+
+    /// 借用冲突的错误
+    fn borrowed(&self) -> SunError {
+        SunError::RunError(format!("`{}` is already borrowed", self.name))
+    }
+
+    /// 类型不符的错误
+    fn mismatch<T: Any>(&self) -> SunError {
+        SunError::TypeError(format!("`{}` is not `{}`", self.name, type_name::<T>()))
+    }
+
+    /// 是否为同一个宿主对象
+    pub fn ptr_eq(&self, other: &UserData) -> bool {
+        Rc::ptr_eq(&self.data, &other.data)
+    }
+}
+
+impl fmt::Display for UserData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.name)
+    }
+}
+
+impl fmt::Debug for UserData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UserData({})", self.name)
+    }
+}
@@ -1,6 +1,8 @@
 use crate::{
-    container::{Class, Function, RustFunction, SysFunction, Table},
-    utils::{log::error_output, SunError},
+    container::{
+        Class, Closure, Function, RustFunction, SysFunction, Table, UserData, UserFunction,
+    },
+    utils::SunError,
 };
 use std::borrow::Cow;
 use std::cmp::Ordering;
@@ -11,11 +13,13 @@ use std::fmt;
 pub enum SunValue {
     Nil,
     Boolean(bool),
-    Number(f64),
+    Integer(i64),
+    Float(f64),
     String(Vec<u8>),
     Table(Table),
     Function(Function),
     Class(Class),
+    UserData(UserData),
 }
 
 impl SunValue {
@@ -23,11 +27,13 @@ impl SunValue {
         match self {
             SunValue::Nil => "Nil",
             SunValue::Boolean(_) => "Bool",
-            SunValue::Number(_) => "Number",
+            SunValue::Integer(_) => "Integer",
+            SunValue::Float(_) => "Float",
             SunValue::String(_) => "String",
             SunValue::Table(_) => "Table",
             SunValue::Function(_) => "Function",
             SunValue::Class(c) => c.get_name(),
+            SunValue::UserData(u) => u.get_name(),
         }
     }
 }
@@ -44,9 +50,15 @@ impl From<bool> for SunValue {
     }
 }
 
+impl From<i64> for SunValue {
+    fn from(value: i64) -> Self {
+        SunValue::Integer(value)
+    }
+}
+
 impl From<f64> for SunValue {
     fn from(value: f64) -> Self {
-        SunValue::Number(value)
+        SunValue::Float(value)
     }
 }
 
@@ -74,31 +86,33 @@ impl From<&[u8]> for SunValue {
     }
 }
 
-impl<'a> From<&'a SunValue> for &'a [u8] {
-    fn from(value: &'a SunValue) -> Self {
+impl<'a> TryFrom<&'a SunValue> for &'a [u8] {
+    type Error = SunError;
+
+    fn try_from(value: &'a SunValue) -> Result<Self, Self::Error> {
         if let SunValue::String(s) = value {
-            s
+            Ok(s)
         } else {
-            let e = SunError::ParaError(format!("invalid string"));
-            error_output(e);
+            Err(SunError::ParaError("invalid string".to_string()))
         }
     }
 }
 
-impl<'a> From<&'a SunValue> for Cow<'a, str> {
-    fn from(value: &'a SunValue) -> Self {
-        if let SunValue::String(s) = value {
-            Cow::from(String::from_utf8_lossy(s.as_slice()))
-        } else {
-            let e = SunError::ParaError(format!("invalid string"));
-            error_output(e);
-        }
+impl<'a> TryFrom<&'a SunValue> for Cow<'a, str> {
+    type Error = SunError;
+
+    fn try_from(value: &'a SunValue) -> Result<Self, Self::Error> {
+        let s: &[u8] = value.try_into()?;
+        Ok(String::from_utf8_lossy(s))
     }
 }
 
-impl From<&SunValue> for String {
-    fn from(value: &SunValue) -> Self {
-        String::from_utf8_lossy(value.into()).to_string()
+impl TryFrom<&SunValue> for String {
+    type Error = SunError;
+
+    fn try_from(value: &SunValue) -> Result<Self, Self::Error> {
+        let s: Cow<str> = value.try_into()?;
+        Ok(s.to_string())
     }
 }
 
@@ -126,28 +140,45 @@ impl From<SysFunction> for SunValue {
     }
 }
 
+impl From<UserFunction> for SunValue {
+    fn from(value: UserFunction) -> Self {
+        SunValue::Function(value.into())
+    }
+}
+
+impl From<Closure> for SunValue {
+    fn from(value: Closure) -> Self {
+        SunValue::Function(value.into())
+    }
+}
+
 impl From<Class> for SunValue {
     fn from(value: Class) -> Self {
         SunValue::Class(value)
     }
 }
 
+impl From<UserData> for SunValue {
+    fn from(value: UserData) -> Self {
+        SunValue::UserData(value)
+    }
+}
+
 impl fmt::Display for SunValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SunValue::Nil => write!(f, "nil"),
             SunValue::Boolean(b) => write!(f, "{}", b),
-            SunValue::Number(n) => write!(f, "{}", n),
+            SunValue::Integer(n) => write!(f, "{}", n),
+            // 以 `1.0` 的形式输出整数值的浮点数，与 `Integer` 区分
+            SunValue::Float(n) => write!(f, "{:?}", n),
             SunValue::String(s) => {
-                write!(
-                    f,
-                    "{}",
-                    Cow::from(String::from_utf8_lossy(s.as_slice())).to_string()
-                )
+                write!(f, "{}", String::from_utf8_lossy(s.as_slice()))
             }
             SunValue::Table(t) => write!(f, "{}", t),
             SunValue::Function(p) => write!(f, "{}", p),
             SunValue::Class(c) => write!(f, "{}", c),
+            SunValue::UserData(u) => write!(f, "{}", u),
         }
     }
 }
@@ -157,17 +188,15 @@ impl fmt::Debug for SunValue {
         match self {
             SunValue::Nil => write!(f, "Nil"),
             SunValue::Boolean(b) => write!(f, "Bool({})", b),
-            SunValue::Number(n) => write!(f, "Number({})", n),
+            SunValue::Integer(n) => write!(f, "Integer({})", n),
+            SunValue::Float(n) => write!(f, "Float({:?})", n),
             SunValue::String(s) => {
-                write!(
-                    f,
-                    "String({})",
-                    Cow::from(String::from_utf8_lossy(s.as_slice())).to_string()
-                )
+                write!(f, "String({})", String::from_utf8_lossy(s.as_slice()))
             }
             SunValue::Table(t) => write!(f, "Table({})", t),
             SunValue::Function(p) => write!(f, "Function({})", p),
             SunValue::Class(c) => write!(f, "Class({})", c.get_name()),
+            SunValue::UserData(u) => write!(f, "{:?}", u),
         }
     }
 }
@@ -176,9 +205,11 @@ impl PartialEq for SunValue {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (SunValue::Boolean(x), SunValue::Boolean(y)) => x == y,
-            (SunValue::Number(x), SunValue::Number(y)) => x == y,
+            (SunValue::Integer(x), SunValue::Integer(y)) => x == y,
+            (SunValue::Float(x), SunValue::Float(y)) => x == y,
             (SunValue::String(x), SunValue::String(y)) => x == y,
             (SunValue::Table(x), SunValue::Table(y)) => x == y,
+            (SunValue::UserData(x), SunValue::UserData(y)) => x.ptr_eq(y),
             _ => false,
         }
     }
@@ -190,7 +221,8 @@ impl PartialOrd for SunValue {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match (self, other) {
             (SunValue::Boolean(x), SunValue::Boolean(y)) => Some(x.cmp(y)),
-            (SunValue::Number(x), SunValue::Number(y)) => x.partial_cmp(y),
+            (SunValue::Integer(x), SunValue::Integer(y)) => Some(x.cmp(y)),
+            (SunValue::Float(x), SunValue::Float(y)) => x.partial_cmp(y),
             _ => None,
         }
     }
@@ -42,7 +42,7 @@ impl SunMeta {
 
     /// 获取所有的方法
     pub fn get_methods(&self) -> Vec<&str> {
-        self.methods.iter().map(|(key, _)| key.as_str()).collect()
+        self.methods.keys().map(|key| key.as_str()).collect()
     }
 
     /// 获取类型名
@@ -70,7 +70,7 @@ macro_rules! add_metas {
     ($map:expr, $(($name:expr, $meta:ty)),+) => {
         use sun_core::meta::OwnSunMeta;
         $(
-            $map.insert($name, <$meta>::new().get_meta().clone());
+            $map.insert($name.to_string(), <$meta>::new().get_meta().clone());
         )+
     };
 }
@@ -4,5 +4,32 @@ pub mod caller;
 pub mod container;
 /// 类型转换方法
 pub mod converter;
-/// 操作符元方法
+/// 操作符元方法，返回新的值，不修改参数指向的值
 pub mod op;
+
+use crate::{
+    container::SunValue,
+    utils::{SunError, SunPointer},
+};
+
+/// 检查元方法的参数数量，`n`: 包括调用者在内需要的参数数量
+pub fn check_args(args: &[SunPointer], n: usize) -> Result<(), SunError> {
+    if args.len() >= n {
+        return Ok(());
+    }
+    let e = SunError::ParaError(format!(
+        "need `{}` but provide `{}` parameters",
+        n.saturating_sub(1),
+        args.len().saturating_sub(1)
+    ));
+    Err(e)
+}
+
+/// 二元运算不支持操作数的类型时的错误 `op`: 运算符
+pub fn mismatch(op: &str, left: &SunValue, right: &SunValue) -> SunError {
+    SunError::TypeError(format!(
+        "unsupported operand types for `{op}`: `{}` and `{}`",
+        left.get_name(),
+        right.get_name()
+    ))
+}
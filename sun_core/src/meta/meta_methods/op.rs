@@ -80,7 +80,7 @@ where
     fn not() -> Function;
 }
 
-/// `^`
+/// `^^`
 pub trait XorAble
 where
     Self: OwnSunMeta,
@@ -88,6 +88,22 @@ where
     fn xor() -> Function;
 }
 
+/// `^`
+pub trait PowAble
+where
+    Self: OwnSunMeta,
+{
+    fn pow() -> Function;
+}
+
+/// 阶乘
+pub trait FacAble
+where
+    Self: OwnSunMeta,
+{
+    fn fac() -> Function;
+}
+
 /// 是否可相等
 pub trait EqualAble
 where
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod meta;
 pub mod meta_methods;
 
@@ -1,6 +1,6 @@
 use crate::{
     meta::{OwnSunMeta, SunBase, SunMeta},
-    utils::{object::SunObject, SunPointer},
+    utils::SunPointer,
 };
 use std::collections::HashMap;
 
@@ -28,19 +28,23 @@ impl OwnSunMeta for SunClass {
     }
 }
 
+/// 原生库导出的内容
 #[derive(Debug)]
 pub struct ExportLib {
-    pub meta: HashMap<String, SunObject>,
+    /// 库定义的类型的元数据，导入时合并到虚拟机的 meta 表
+    pub meta: HashMap<String, SunMeta>,
+    /// 库导出的值，导入后组成库的 `Table`
     pub value: HashMap<String, SunPointer>,
 }
 
 impl ExportLib {
-    pub fn new(meta: HashMap<String, SunObject>, value: HashMap<String, SunPointer>) -> Self {
+    /// 新建导出内容 `meta`: 类型名到元数据的字典 `value`: 变量名到值的字典
+    pub fn new(meta: HashMap<String, SunMeta>, value: HashMap<String, SunPointer>) -> Self {
         ExportLib { meta, value }
     }
 }
 
-/// 导出包
+/// 可以被脚本通过 `@"name"` 导入的原生库
 pub trait ImportAble {
     /// 返回值为元数据的字典和数据的字典
     fn export_lib() -> ExportLib;
@@ -0,0 +1,249 @@
+use crate::container::{Capture, SunValue, TableKey};
+use std::collections::HashMap;
+
+/// Sun 虚拟机的指令集
+#[derive(Debug)]
+pub enum Command {
+    /**
+        从全局变量表加载值到栈上
+        + `name`: `String` 变量名
+    */
+    LoadValue(String),
+
+    /**
+        将栈上值存储到全局变量表中
+        + `name`: `String` 变量名
+    */
+    StoreGlobal(String),
+
+    /**
+        从当前调用帧加载局部变量到栈上
+        + `slot`: `usize` 局部变量的槽位
+    */
+    LoadLocal(usize),
+
+    /**
+        将栈上值存储到当前调用帧的局部变量中
+        + `slot`: `usize` 局部变量的槽位
+    */
+    StoreLocal(usize),
+
+    /**
+        为局部变量创建新的存储单元，之前创建的闭包仍然持有旧的存储单元
+        + `slot`: `usize` 局部变量的槽位
+    */
+    NewLocal(usize),
+
+    /**
+        从当前闭包捕获的变量加载值到栈上
+        + `index`: `usize` 捕获变量的序号
+    */
+    LoadUpvalue(usize),
+
+    /**
+        将栈上值存储到当前闭包捕获的变量中
+        + `index`: `usize` 捕获变量的序号
+    */
+    StoreUpvalue(usize),
+
+    /**
+        将栈顶的脚本函数与捕获的变量组合成闭包
+        + `captures`: `Vec<Capture>` 捕获变量的来源
+    */
+    MakeClosure(Vec<Capture>),
+
+    /**
+        直接加载值到栈上
+        + `value`: `SunValue` 值
+    */
+    LoadConst(SunValue),
+
+    /**
+        获取对象的方法
+        + `method_name`: `String` 方法名
+    */
+    LoadMethod(String),
+
+    /**
+        创建表的指令
+        + `number`: `usize` 内容个数
+    */
+    CreateTable(usize),
+
+    /**
+        依次弹出键和值，写入栈顶的 `Table`，键不能作为 `TableKey` 时抛出 `KeyError`
+    */
+    SetPair,
+
+    /**
+        处理对Table的赋值
+    */
+    SetTable,
+
+    /**
+        依次弹出 `Table`、键和值，将值写入键对应的位置，键不存在时添加到字典中
+    */
+    SetIndex,
+
+    /**
+        依次弹出对象和属性名，实例的属性或 `Table` 中的键存在时压入对应的值，否则压入对象的类型方法
+    */
+    LoadAttr,
+
+    /**
+        依次弹出实例或 `Table`、属性名和值，设置实例的属性或 `Table` 中键对应的值
+    */
+    SetAttr,
+
+    /**
+        创建类的空实例压入栈上
+        + `class_name`: `String` 类名
+    */
+    NewInstance(String),
+
+    /**
+        依次弹出构造函数和各个方法，注册类的元数据，再将构造函数压入栈上
+        + `class_name`: `String` 类名
+        + `base`: `Option<String>` 基类名
+        + `methods`: `Vec<String>` 方法名
+    */
+    DefineClass(String, Option<String>, Vec<String>),
+
+    /**
+        调用函数
+        + `para_number`: `usize` 参数个数
+    */
+    Call(usize),
+
+    /**
+        依次弹出对象和方法名，调用对象的类型方法，对象作为第一个参数；
+        没有该方法时调用 `Table` 中键对应的值或实例的属性，不传入对象
+        + `para_number`: `usize` 参数个数，不含对象
+    */
+    CallMethod(usize),
+
+    /*
+        条件跳转
+        + `jump`: `usize` 跳转的位置的偏移
+    */
+    TestJump(usize),
+
+    /*
+        无条件跳转
+        + `jump`: `usize` 跳转的位置偏移
+    */
+    Jump(usize),
+
+    /*
+        无条件反向跳转
+        + `jump`: `usize` 跳转的位置偏移
+    */
+    Back(usize),
+
+    /*
+        从当前函数返回，栈顶的值作为返回值
+    */
+    Return,
+
+    /*
+        进入有值的语句块，记录当前栈的长度
+    */
+    BlockStart,
+
+    /*
+        离开有值的语句块，栈顶的值作为语句块的值，丢弃语句块中产生的其他值，没有值时为 `Nil`
+    */
+    BlockValue,
+
+    /*
+        通过 `break` 或 `continue` 离开有值的语句块，丢弃语句块中产生的值
+    */
+    BlockExit,
+
+    /*
+        弹出栈顶的值，能在跳转表中找到时跳转，否则执行下一条指令
+        + `table`: `HashMap<TableKey, usize>` 值和跳转的位置偏移
+    */
+    Switch(HashMap<TableKey, usize>),
+
+    /*
+        弹出栈顶的值，是包含所有键的 `Table` 时将键对应的值压入栈上，第一个值位于栈顶，否则跳转
+        + `keys`: `Vec<String>` 键名
+        + `jump`: `usize` 跳转的位置偏移
+    */
+    MatchTable(Vec<String>, usize),
+
+    /*
+        弹出栈顶的值，是位于 `[start, end)` 区间内的数字时执行下一条指令，否则跳转
+        + `start`: `SunValue` 区间的起始值
+        + `end`: `SunValue` 区间的结束值，不含在区间内
+        + `jump`: `usize` 跳转的位置偏移
+    */
+    MatchRange(SunValue, SunValue, usize),
+
+    /*
+        进入 try 语句块，出错时跳转到 catch 语句块，错误信息压入栈上
+        + `jump`: `usize` catch 语句块的位置偏移
+    */
+    Try(usize),
+
+    /*
+        离开 try 语句块
+    */
+    EndTry,
+
+    /*
+        将栈顶的 `Table` 转换为迭代器，压入当前调用帧的迭代器栈
+    */
+    Iter,
+
+    /*
+        将栈上的开始值和结束值转换为数字区间的迭代器，压入当前调用帧的迭代器栈
+    */
+    IterRange,
+
+    /*
+        获取迭代器的下一个值压入栈上，迭代结束时跳转
+        + `jump`: `usize` 跳转的位置偏移
+    */
+    IterNext(usize),
+
+    /*
+        弹出当前调用帧的迭代器
+    */
+    IterEnd,
+
+    /*
+        将栈顶 `Table` 数组部分的前 `n` 个值压入栈上，第一个值位于栈顶，缺少的值为 `Nil`
+        + `n`: `usize` 值的个数
+    */
+    Unpack(usize),
+
+    /*
+        解构栈顶 `Table` 的数组部分，将前 `n` 个值压入栈上，第一个值位于栈顶
+        + `n`: `usize` 值的个数
+        + `rest`: `bool` 是否先将剩余的值组成新的 `Table` 压入栈上
+        + `strict`: `bool` 值的个数不符时抛出 `IndexError`，否则缺少的值为 `Nil`
+    */
+    UnpackArray(usize, bool, bool),
+
+    /*
+        解构栈顶 `Table` 的字典部分，将键对应的值压入栈上，第一个值位于栈顶
+        + `keys`: `Vec<String>` 键名
+        + `strict`: `bool` 缺少键时抛出 `KeyError`，否则值为 `Nil`
+    */
+    UnpackKeys(Vec<String>, bool),
+
+    /*
+        导入模块
+        + `lib_name`: `String` 模块的地址
+    */
+    Import(String),
+
+    /*
+        元调用
+        + `meta_name`: `String` 类型名称
+        + `method_name`: `String` 方法名
+    */
+    LoadMetamethod(String, String),
+}
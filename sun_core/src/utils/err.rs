@@ -1,3 +1,4 @@
+use crate::utils::Span;
 use colorized::*;
 use std::error::Error;
 use std::fmt;
@@ -29,6 +30,127 @@ pub enum SunError {
     AttributeError(String),
     /// `InputError`: 输入错误，解释器启动阶段触发，接收到错误的sun文件或者命令行输入时触发
     InputError(String),
+    /// `UserError`: 脚本中使用 `error` 函数抛出的错误
+    UserError(String),
+    /// `ArithmeticError`: 算术错误，整数运算溢出或整数除以零，虚拟机指令执行阶段
+    ArithmeticError(String),
+    /// `ImportError`: 导入模块错误，找不到模块文件或模块循环导入
+    ImportError(String),
+    /// `TraceError`: 附带出错位置和调用栈的运行时错误，由内向外记录每层调用帧的位置和函数名
+    TraceError(Box<SunError>, Vec<(Span, String)>),
+    /// `SourceError`: 附带源码诊断信息的词法或语法分析错误
+    SourceError(Box<SunError>, Diagnostic),
+}
+
+/// 源码诊断信息，指出出错的 `Token` 所在的源码行
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// 出错的位置
+    pub span: Span,
+    /// 出错位置所在行的源码
+    pub source: String,
+    /// 帮助信息
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    /// 创建诊断信息 `span`: 出错的位置 `source`: 所在行的源码
+    pub fn new(span: Span, source: String) -> Self {
+        Diagnostic {
+            span,
+            source,
+            help: None,
+        }
+    }
+
+    /// 附上帮助信息
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+}
+
+/// 按照 rustc 的格式输出源码行，并在出错的列下方标记 `^`
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self.span.line.to_string();
+        let gutter = " ".repeat(line.len());
+        let bar = "|".color(Colors::BrightBlueFg);
+        // 保留制表符，使 `^` 与源码对齐
+        let end = (self.span.col.max(1) as usize - 1).min(self.source.len());
+        let indent: String = String::from_utf8_lossy(&self.source.as_bytes()[..end])
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        writeln!(
+            f,
+            "{gutter}{} {}",
+            "-->".color(Colors::BrightBlueFg),
+            self.span
+        )?;
+        writeln!(f, "{gutter} {bar}")?;
+        writeln!(
+            f,
+            "{} {bar} {}",
+            line.color(Colors::BrightBlueFg),
+            self.source
+        )?;
+        write!(f, "{gutter} {bar} {indent}{}", "^".color(Colors::RedFg))?;
+        if let Some(help) = &self.help {
+            write!(
+                f,
+                "\n{gutter} {} help: {help}",
+                "=".color(Colors::BrightBlueFg)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl SunError {
+    /// 获取错误类型名
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::TokenizerError(_) => "TokenizerError",
+            Self::NumberError(_) => "NumberError",
+            Self::SymbolError(_) => "SymbolError",
+            Self::CallError(_) => "CallError",
+            Self::ParaError(_) => "ParaError",
+            Self::AssignError(_) => "AssignError",
+            Self::KeyError(_) => "KeyError",
+            Self::IndexError(_) => "IndexError",
+            Self::RunError(_) => "RunError",
+            Self::TypeError(_) => "TypeError",
+            Self::AttributeError(_) => "AttributeError",
+            Self::InputError(_) => "InputError",
+            Self::UserError(_) => "UserError",
+            Self::ArithmeticError(_) => "ArithmeticError",
+            Self::ImportError(_) => "ImportError",
+            Self::TraceError(e, _) | Self::SourceError(e, _) => e.kind(),
+        }
+    }
+
+    /// 获取错误信息
+    pub fn message(&self) -> &str {
+        match self {
+            Self::TokenizerError(s)
+            | Self::NumberError(s)
+            | Self::SymbolError(s)
+            | Self::CallError(s)
+            | Self::ParaError(s)
+            | Self::AssignError(s)
+            | Self::KeyError(s)
+            | Self::IndexError(s)
+            | Self::RunError(s)
+            | Self::TypeError(s)
+            | Self::AttributeError(s)
+            | Self::InputError(s)
+            | Self::UserError(s)
+            | Self::ArithmeticError(s)
+            | Self::ImportError(s) => s,
+            Self::TraceError(e, _) | Self::SourceError(e, _) => e.message(),
+        }
+    }
 }
 
 impl fmt::Display for SunError {
@@ -60,6 +182,30 @@ impl fmt::Display for SunError {
             Self::TypeError(s) => write!(f, "{} ({s})", "TypeError".color(Colors::RedFg)),
             Self::AttributeError(s) => write!(f, "{} ({s})", "AttributeError".color(Colors::RedFg)),
             Self::InputError(s) => write!(f, "{} ({s})", "InputError".color(Colors::RedFg)),
+            Self::UserError(s) => write!(f, "{} ({s})", "UserError".color(Colors::RedFg)),
+            Self::ArithmeticError(s) => {
+                write!(f, "{} ({s})", "ArithmeticError".color(Colors::RedFg))
+            }
+            Self::ImportError(s) => write!(f, "{} ({s})", "ImportError".color(Colors::RedFg)),
+            Self::TraceError(e, trace) => {
+                write!(f, "{e}")?;
+                // 连续相同的调用帧（如递归）只输出一次
+                let mut i = 0;
+                while i < trace.len() {
+                    let (span, name) = &trace[i];
+                    let repeat = trace[i..]
+                        .iter()
+                        .take_while(|frame| *frame == &trace[i])
+                        .count();
+                    write!(f, "\n    at {span} in {name}")?;
+                    if repeat > 1 {
+                        write!(f, "\n    ... repeated {} more times", repeat - 1)?;
+                    }
+                    i += repeat;
+                }
+                Ok(())
+            }
+            Self::SourceError(e, diagnostic) => write!(f, "{e}\n{diagnostic}"),
         }
     }
 }
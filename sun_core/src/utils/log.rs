@@ -1,16 +1,23 @@
 use colorized::*;
 use std::error::Error;
 use std::fmt::{Debug, Display};
-use std::process;
 
 /// 输出错误信息
-pub fn error_output<E>(error: E) -> !
+pub fn error_output<E>(error: E)
 where
     E: Error,
 {
     eprint!("{}", "[e] ".color(Colors::RedFg));
-    eprintln!("{} use `--debug` to get more information", error);
-    process::exit(0);
+    // 多行的错误信息（源码诊断、调用栈）在提示之后输出
+    let error = error.to_string();
+    let (head, rest) = match error.split_once('\n') {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (error.as_str(), None),
+    };
+    eprintln!("{} use `--debug` to get more information", head);
+    if let Some(rest) = rest {
+        eprintln!("{rest}");
+    }
 }
 
 /// 输出警告信息
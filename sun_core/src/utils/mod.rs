@@ -1,10 +1,14 @@
+pub mod command;
 pub mod err;
 pub mod log;
 pub mod machine;
 pub mod object;
+pub mod span;
 pub mod sun_pointer;
 
-pub use err::SunError;
+pub use command::Command;
+pub use err::{Diagnostic, SunError};
 pub use machine::IsMachine;
 pub use object::SunObject;
+pub use span::Span;
 pub use sun_pointer::SunPointer;
@@ -1,11 +1,8 @@
 use crate::{
     add_methods,
     container::{Function, RustFunction, SysFunction},
-    meta::{OwnSunMeta, SunBase, SunMeta},
-    utils::{
-        log::{error_output, log_output},
-        IsMachine, SunError, SunPointer,
-    },
+    meta::{meta_methods::check_args, OwnSunMeta, SunBase, SunMeta},
+    utils::{log::log_output, IsMachine, SunError, SunPointer},
 };
 
 /// `SunObject` 元数据
@@ -23,21 +20,29 @@ impl SunObject {
     }
 }
 
+impl Default for SunObject {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// 获取类型名的类型方法
 pub fn _type() -> Function {
-    let f = |value: Vec<SunPointer>| -> Vec<SunPointer> {
+    let f = |value: Vec<SunPointer>| -> Result<Vec<SunPointer>, SunError> {
+        check_args(&value, 1)?;
         let value = value[0].get();
         log_output(value.get_name());
-        vec![]
+        Ok(vec![])
     };
     Function::from(f as RustFunction)
 }
 
 /// 获取数据的拷贝
 pub fn clone() -> Function {
-    let f = |value: Vec<SunPointer>| -> Vec<SunPointer> {
+    let f = |value: Vec<SunPointer>| -> Result<Vec<SunPointer>, SunError> {
+        check_args(&value, 1)?;
         let res = value[0].deep_copy();
-        vec![res]
+        Ok(vec![res])
     };
     Function::from(f as RustFunction)
 }
@@ -45,23 +50,21 @@ pub fn clone() -> Function {
 /// 获取类型的元数据的类型方法
 pub fn get_meta() -> Function {
     let f = |vm: &mut dyn IsMachine| match vm.pop() {
-        Some(p) => match p.get() {
-            value => {
-                if let Some(res) = vm.get_meta(value.get_name()) {
-                    let res = res.join(", ");
-                    log_output(res)
-                } else {
-                    let e = SunError::TypeError(format!(
-                        "`{}` is not a valid sun type",
-                        value.get_name()
-                    ));
-                    error_output(e);
-                }
+        Some(p) => {
+            let value = p.get();
+            if let Some(res) = vm.get_meta(value.get_name()) {
+                let res = res.join(", ");
+                log_output(res);
+                Ok(())
+            } else {
+                let e =
+                    SunError::TypeError(format!("`{}` is not a valid sun type", value.get_name()));
+                Err(e)
             }
-        },
+        }
         None => {
-            let e = SunError::RunError(format!("stack is empty so failed to find attribute"));
-            error_output(e);
+            let e = SunError::RunError("stack is empty so failed to find attribute".to_string());
+            Err(e)
         }
     };
     Function::from(f as SysFunction)
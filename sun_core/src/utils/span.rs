@@ -0,0 +1,32 @@
+use std::fmt;
+use std::rc::Rc;
+
+/// 源码中的位置
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Span {
+    /// 源文件名
+    pub file: Rc<str>,
+    /// 行号
+    pub line: u64,
+    /// 列号
+    pub col: u64,
+}
+
+impl Span {
+    /// 创建新的源码位置
+    pub fn new(file: Rc<str>, line: u64, col: u64) -> Self {
+        Span { file, line, col }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.col)
+    }
+}
+
+impl fmt::Debug for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
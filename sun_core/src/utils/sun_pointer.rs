@@ -19,12 +19,12 @@ impl SunPointer {
     }
 
     /// 获取引用
-    pub fn borrow(&self) -> Ref<SunValue> {
+    pub fn borrow(&self) -> Ref<'_, SunValue> {
         self.pointer.borrow()
     }
 
     /// 获取可变引用
-    pub fn borrow_mut(&self) -> RefMut<SunValue> {
+    pub fn borrow_mut(&self) -> RefMut<'_, SunValue> {
         self.pointer.borrow_mut()
     }
 
@@ -36,6 +36,14 @@ impl SunPointer {
     pub fn deep_copy(&self) -> Self {
         SunPointer::new(self.get())
     }
+
+    /// 赋值时使用的指针，`Table`、类的实例和宿主对象保持引用，其他类型的值拷贝到新的指针
+    pub fn copy_value(&self) -> Self {
+        match &*self.borrow() {
+            SunValue::Table(_) | SunValue::Class(_) | SunValue::UserData(_) => self.clone(),
+            other => SunPointer::new(other.clone()),
+        }
+    }
 }
 
 impl Deref for SunPointer {
@@ -1 +1,4 @@
+//! Sun 的原生标准库，每个库实现 `ImportAble`，由虚拟机注册后通过 `@"name"` 导入
+pub mod math;
 
+pub use math::Math;
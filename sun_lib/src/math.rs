@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use sun_core::{
+    container::{Function, RustFunction, SunValue},
+    utils::{SunError, SunPointer},
+    ExportLib, ImportAble,
+};
+
+/// 数学库，使用 `@"math"` 导入
+pub struct Math;
+
+impl ImportAble for Math {
+    fn export_lib() -> ExportLib {
+        let mut value = HashMap::new();
+        let functions: [(&str, RustFunction); 11] = [
+            ("sqrt", sqrt),
+            ("exp", exp),
+            ("ln", ln),
+            ("sin", sin),
+            ("cos", cos),
+            ("tan", tan),
+            ("floor", floor),
+            ("ceil", ceil),
+            ("abs", abs),
+            ("max", max),
+            ("min", min),
+        ];
+        for (name, f) in functions {
+            value.insert(
+                name.to_string(),
+                SunPointer::new(SunValue::from(Function::from(f))),
+            );
+        }
+        for (name, n) in [("pi", std::f64::consts::PI), ("e", std::f64::consts::E)] {
+            value.insert(name.to_string(), SunPointer::new(SunValue::from(n)));
+        }
+        ExportLib::new(HashMap::new(), value)
+    }
+}
+
+/// 获取第 `i` 个参数的数值 `name`: 函数名
+fn number(args: &[SunPointer], i: usize, name: &str) -> Result<SunValue, SunError> {
+    match args.get(i).map(|arg| arg.get()) {
+        Some(n @ (SunValue::Integer(_) | SunValue::Float(_))) => Ok(n),
+        Some(other) => {
+            let e = SunError::ParaError(format!("`{name}` needs a number but got `{other}`"));
+            Err(e)
+        }
+        None => {
+            let e = SunError::ParaError(format!("the number of parameters of `{name}` is too few"));
+            Err(e)
+        }
+    }
+}
+
+/// 将数字转换为浮点数
+fn to_float(value: &SunValue) -> f64 {
+    match value {
+        SunValue::Integer(n) => *n as f64,
+        SunValue::Float(n) => *n,
+        _ => f64::NAN,
+    }
+}
+
+/// 批量定义参数为一个数字、返回浮点数的函数
+macro_rules! float_functions {
+    ($($name:ident),+) => {
+        $(
+            fn $name(args: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
+                let n = to_float(&number(&args, 0, stringify!($name))?);
+                Ok(vec![SunPointer::new(SunValue::from(n.$name()))])
+            }
+        )+
+    };
+}
+
+float_functions!(sqrt, exp, ln, sin, cos, tan);
+
+/// 向下取整，整数保持不变
+fn floor(args: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
+    let res = match number(&args, 0, "floor")? {
+        SunValue::Float(n) => SunValue::from(n.floor()),
+        n => n,
+    };
+    Ok(vec![SunPointer::new(res)])
+}
+
+/// 向上取整，整数保持不变
+fn ceil(args: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
+    let res = match number(&args, 0, "ceil")? {
+        SunValue::Float(n) => SunValue::from(n.ceil()),
+        n => n,
+    };
+    Ok(vec![SunPointer::new(res)])
+}
+
+/// 绝对值，整数溢出时抛出 `ArithmeticError`
+fn abs(args: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
+    let res = match number(&args, 0, "abs")? {
+        SunValue::Integer(n) => match n.checked_abs() {
+            Some(n) => SunValue::from(n),
+            None => {
+                let e = SunError::ArithmeticError(format!("`abs({n})` overflowed"));
+                return Err(e);
+            }
+        },
+        n => SunValue::from(to_float(&n).abs()),
+    };
+    Ok(vec![SunPointer::new(res)])
+}
+
+/// 返回参数中按 `pick` 选出的数字 `name`: 函数名
+fn select(
+    args: &[SunPointer],
+    name: &str,
+    pick: fn(f64, f64) -> bool,
+) -> Result<Vec<SunPointer>, SunError> {
+    let mut res = number(args, 0, name)?;
+    for i in 1..args.len() {
+        let n = number(args, i, name)?;
+        if pick(to_float(&n), to_float(&res)) {
+            res = n;
+        }
+    }
+    Ok(vec![SunPointer::new(res)])
+}
+
+/// 参数中最大的数字
+fn max(args: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
+    select(&args, "max", |n, res| n > res)
+}
+
+/// 参数中最小的数字
+fn min(args: Vec<SunPointer>) -> Result<Vec<SunPointer>, SunError> {
+    select(&args, "min", |n, res| n < res)
+}